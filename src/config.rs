@@ -0,0 +1,297 @@
+//! This module reads the config file and turns its "modules" array into per-module settings, and its "logo" object into logo settings
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::{env, fmt, fs, io};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ASSETS;
+use crate::render;
+
+/// Settings of a single module entry from the config file
+/// An entry is either a plain module name like `"disks"`, or an object like `{"type": "disks", "key": "Storage", "show_fs": false}`
+#[derive(Clone, Debug, Default)]
+pub struct ModuleConfig {
+    /// Name of the module, eg. "disks"
+    pub module: String,
+    /// Label overriding the module's default key
+    pub key: Option<String>,
+    /// Template with named placeholders replacing the module's default output, eg. `"{used_gib:.1} / {total_gib:.1} GiB"`
+    pub format: Option<String>,
+    /// All other fields of the entry, interpreted by the module itself
+    pub options: Map<String, Value>,
+}
+
+impl ModuleConfig {
+    /// Parses one entry of the "modules" array, returns None if it's neither a string nor an object with "type"
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(module) => Some(ModuleConfig { module: module.clone(), ..Default::default() }),
+            Value::Object(entry) => {
+                let mut options = entry.clone();
+                let module = options.remove("type")?.as_str()?.to_string();
+                let key = options.remove("key").and_then(|key| key.as_str().map(str::to_string));
+                let format = options.remove("format").and_then(|format| format.as_str().map(str::to_string));
+                Some(ModuleConfig { module, key, format, options })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a boolean option, or the default if it's missing or not a boolean
+    pub fn bool_option(&self, name: &str, default: bool) -> bool {
+        self.options.get(name).and_then(Value::as_bool).unwrap_or(default)
+    }
+
+    /// Returns a numeric option, or the default if it's missing or not a number
+    pub fn f64_option(&self, name: &str, default: f64) -> f64 {
+        self.options.get(name).and_then(Value::as_f64).unwrap_or(default)
+    }
+
+    /// Returns a list of strings option, ignoring elements that are not strings
+    pub fn str_list_option(&self, name: &str) -> Option<Vec<String>> {
+        self.options.get(name).and_then(Value::as_array).map(|list| {
+            list.iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+    }
+}
+
+/// Settings of image logos from the "logo" object of the config file, eg. `"logo": {"width": 30, "cell_width": 9}`
+/// Command line arguments take precedence over them
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogoConfig {
+    /// Width of the logo in character cells
+    pub width: Option<usize>,
+    /// Height of the logo in character cells
+    pub height: Option<usize>,
+    /// Size of a character cell in pixels, used to scale sixel images
+    pub cell_width: Option<usize>,
+    pub cell_height: Option<usize>,
+    /// Keep the active color between lines of text logos, like fastfetch
+    pub color_carry: Option<bool>,
+}
+
+impl LogoConfig {
+    /// Reads the "logo" object, fields that are missing or not positive numbers are left unset
+    fn from_value(value: &Value) -> Self {
+        let field = |name: &str| value.get(name).and_then(Value::as_u64).filter(|number| *number > 0).map(|number| number as usize);
+        LogoConfig {
+            width: field("width"),
+            height: field("height"),
+            cell_width: field("cell_width"),
+            cell_height: field("cell_height"),
+            color_carry: value.get("color_carry").and_then(Value::as_bool),
+        }
+    }
+}
+
+/// Everything read from the config file
+pub struct Config {
+    pub modules: Vec<ModuleConfig>,
+    pub logo: LogoConfig,
+}
+
+/// Errors that can happen while loading the config file
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Config file exists but can't be read
+    Read { path: String, error: io::Error },
+    /// Config file is not valid JSON
+    Parse { path: String, line: usize, column: usize, message: String },
+    /// Config file is valid JSON, but doesn't have the expected structure
+    Invalid { path: String, message: String },
+    /// Default config wasn't written, because a config file already exists
+    Exists { path: String },
+    /// Default config or its backup couldn't be written
+    Write { path: String, error: io::Error },
+    /// Neither XDG_CONFIG_HOME nor HOME is set, so there is no user config directory
+    NoConfigDir,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } => write!(f, "unable to read config file {}: {}", path, error),
+            ConfigError::Parse { path, line, column, message } => {
+                write!(f, "error in config file {} at line {}, column {}: {}", path, line, column, message)
+            }
+            ConfigError::Invalid { path, message } => write!(f, "error in config file {}: {}", path, message),
+            ConfigError::Exists { path } => write!(f, "config file {} already exists, use --force to overwrite it", path),
+            ConfigError::Write { path, error } => write!(f, "unable to write {}: {}", path, error),
+            ConfigError::NoConfigDir => write!(f, "unable to find the config directory, neither $XDG_CONFIG_HOME nor $HOME is set"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Default config embedded in the binary, used when the user has no config file
+pub fn default_config() -> &'static str {
+    ASSETS.get_file("default.json")
+        .and_then(|file| file.contents_utf8())
+        .expect("Embedded default.json is missing")
+}
+
+/// Writes the default config to the given path, creating its directory
+/// An existing file is only replaced if `force` is set, and is first copied to a timestamped backup, whose path is returned
+pub fn write_default_config(path: &Path, force: bool) -> Result<Option<PathBuf>, ConfigError> {
+    let write_error = |path: &Path, error| ConfigError::Write { path: path.display().to_string(), error };
+    let mut backup = None;
+    if path.exists() {
+        if !force {
+            return Err(ConfigError::Exists { path: path.display().to_string() });
+        }
+        let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(format!(".{}.bak", timestamp()));
+        let backup_path = path.with_file_name(backup_name);
+        fs::copy(path, &backup_path).map_err(|error| write_error(&backup_path, error))?;
+        backup = Some(backup_path);
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|error| write_error(dir, error))?;
+    }
+    fs::write(path, default_config()).map_err(|error| write_error(path, error))?;
+    Ok(backup)
+}
+
+/// Current UTC time as YYYYMMDD-HHMMSS, used for naming backups
+fn timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    let (days, day_seconds) = (seconds / 86400, seconds % 86400);
+    // Convert days since 1970-01-01 to a calendar date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year, month, day, day_seconds / 3600, day_seconds % 3600 / 60, day_seconds % 60
+    )
+}
+
+/// Returns the user's rastfetch config directory, or an error if there is none, for paths the user asked for explicitly
+pub fn require_user_config_dir() -> Result<PathBuf, ConfigError> {
+    user_config_dir().ok_or(ConfigError::NoConfigDir)
+}
+
+/// Returns the user's rastfetch config directory, $XDG_CONFIG_HOME/rastfetch or ~/.config/rastfetch
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home_dir| PathBuf::from(home_dir).join(".config")))?;
+    Some(config_home.join("rastfetch"))
+}
+
+/// Returns all places a config file is looked for, from the most to the least important
+/// User config dir comes first, then every directory in $XDG_CONFIG_DIRS (default /etc/xdg), and /etc/rastfetch last
+fn config_search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = user_config_dir() {
+        paths.push(dir.join("config.json"));
+    }
+    let config_dirs = env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/etc/xdg".to_string());
+    for dir in config_dirs.split(':').filter(|dir| Path::new(dir).is_absolute()) {
+        paths.push(Path::new(dir).join("rastfetch/config.json"));
+    }
+    paths.push(PathBuf::from("/etc/rastfetch/config.json"));
+    paths
+}
+
+/// Returns config file in a serde_json readable format, together with the path it was read from
+/// A config file given with --config-file must exist, otherwise the first existing file from the search paths is used,
+/// falling back to the embedded default config if there is none
+fn read_config(config_file: Option<&Path>) -> Result<(String, Value), ConfigError> {
+    let (path, config_data) = match config_file {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(config_data) => (path.display().to_string(), config_data),
+            Err(error) => return Err(ConfigError::Read { path: path.display().to_string(), error }),
+        },
+        None => {
+            let mut found = None;
+            for path in config_search_paths() {
+                match fs::read_to_string(&path) {
+                    Ok(config_data) => {
+                        found = Some((path.display().to_string(), config_data));
+                        break;
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                    Err(error) => return Err(ConfigError::Read { path: path.display().to_string(), error }),
+                }
+            }
+            found.unwrap_or(("default.json".to_string(), default_config().to_string()))
+        }
+    };
+    match serde_json::from_str(&config_data) {
+        Ok(config) => Ok((path, config)),
+        Err(error) => Err(ConfigError::Parse {
+            path,
+            line: error.line(),
+            column: error.column(),
+            // serde_json appends its own position to the message, which is already reported
+            message: error.to_string().split(" at line ").next().unwrap_or_default().to_string(),
+        }),
+    }
+}
+
+/// Returns settings of all modules present in the config file, in order, and the logo settings
+/// Entries that are neither a module name nor an object with "type" are skipped with a warning,
+/// and so is a format string using placeholders the module doesn't have
+pub fn load_config(config_file: Option<&Path>) -> Result<Config, ConfigError> {
+    let (path, config) = read_config(config_file)?;
+    let Some(modules) = config["modules"].as_array() else {
+        return Err(ConfigError::Invalid { path, message: "\"modules\" key is missing or is not an array".to_string() });
+    };
+    let mut module_list = Vec::new();
+    for (index, entry) in modules.iter().enumerate() {
+        match ModuleConfig::from_value(entry) {
+            Some(mut module) => {
+                // Checked once here, so a typo is reported even when the module fails or lacks the field on this machine
+                if let Some(format) = &module.format && let Err(error) = render::check_format(&module.module, format) {
+                    eprintln!("rastfetch: warning: ignoring \"format\" of module \"{}\" in {}: {}", module.module, path, error);
+                    module.format = None;
+                }
+                module_list.push(module);
+            }
+            None => eprintln!(
+                "rastfetch: warning: skipping entry {} in \"modules\" of {}, expected a module name or an object with \"type\", found {}",
+                index + 1, path, entry
+            ),
+        }
+    }
+    Ok(Config { modules: module_list, logo: LogoConfig::from_value(&config["logo"]) })
+}
+
+/// Returns the known module name closest to an unknown one, if it's close enough to be a typo
+pub fn suggest_module<'a>(name: &str, known_modules: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    known_modules
+        .map(|known| (edit_distance(name, known), known))
+        .filter(|(distance, known)| *distance <= 2.max(known.len() / 3))
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,417 @@
+//! This module turns image files into logos, either drawn by the terminal itself using its graphics protocol,
+//! or converted to text with truecolor escapes for terminals without one
+//! Text logos are read in `main`, images end up here and come back as escape sequences covering a block of character cells
+use image::imageops::FilterType;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// Logo printed next to the fetched information
+pub enum Logo {
+    /// Lines of text with $[1-9] color markers
+    Text(Vec<String>),
+    /// Image drawn by the terminal from an escape sequence, which leaves the cursor in the image's top left cell
+    Bitmap { escape: String, columns: usize, rows: usize },
+}
+
+/// Errors that can happen while turning an image into a logo
+#[derive(Debug)]
+pub enum LogoError {
+    /// Image file can't be read
+    Read { path: String, error: io::Error },
+    /// Image file is not in a supported format
+    Format { path: String, message: String },
+}
+
+impl fmt::Display for LogoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogoError::Read { path, error } => write!(f, "unable to read logo image {}: {}", path, error),
+            LogoError::Format { path, message } => write!(f, "unsupported logo image {}: {}", path, message),
+        }
+    }
+}
+
+/// Size of the logo in character cells, a missing dimension is calculated from the image's aspect ratio
+#[derive(Clone, Copy, Default)]
+pub struct CellSize {
+    pub columns: Option<usize>,
+    pub rows: Option<usize>,
+}
+
+/// Width used when neither --logo-width nor --logo-height is given
+const DEFAULT_COLUMNS: usize = 30;
+
+impl CellSize {
+    /// Returns columns and rows for an image of the given size in pixels, assuming cells are twice as high as they are wide
+    fn fit(self, width: u32, height: u32) -> (usize, usize) {
+        let aspect = height as f64 / width.max(1) as f64 / 2.0;
+        match (self.columns, self.rows) {
+            (Some(columns), Some(rows)) => (columns.max(1), rows.max(1)),
+            (None, Some(rows)) => (((rows as f64 / aspect).round() as usize).max(1), rows.max(1)),
+            (columns, None) => {
+                let columns = columns.unwrap_or(DEFAULT_COLUMNS).max(1);
+                (columns, ((columns as f64 * aspect).round() as usize).max(1))
+            }
+        }
+    }
+}
+
+/// Largest payload of a single kitty graphics escape
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Reads a PNG image and builds a logo drawn with the kitty graphics protocol
+pub fn kitty_logo(path: &Path, size: CellSize) -> Result<Logo, LogoError> {
+    let png = fs::read(path).map_err(|error| LogoError::Read { path: path.display().to_string(), error })?;
+    let Some((width, height)) = png_dimensions(&png) else {
+        return Err(LogoError::Format { path: path.display().to_string(), message: "kitty logos must be PNG files".to_string() });
+    };
+    let (columns, rows) = size.fit(width, height);
+    Ok(Logo::Bitmap { escape: kitty_escape(&png, columns, rows), columns, rows })
+}
+
+/// Builds the kitty graphics escapes transmitting and displaying a PNG image scaled to the given cells
+/// f=100 sends the PNG as is, a=T displays it right away, C=1 keeps the cursor in place and q=2 stops the terminal from replying.
+/// Base64 payload is split into chunks, every chunk but the last has m=1, see https://sw.kovidgoyal.net/kitty/graphics-protocol/
+pub fn kitty_escape(png: &[u8], columns: usize, rows: usize) -> String {
+    let payload = base64(png);
+    let chunks: Vec<&str> = payload.as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+    let mut escape = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            escape.push_str(&format!("\x1b_Gf=100,a=T,C=1,q=2,c={},r={},m={};{}\x1b\\", columns, rows, more, chunk));
+        } else {
+            escape.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    escape
+}
+
+/// Size of a character cell in pixels when it's not set in config, typical for a 10pt monospace font
+pub const DEFAULT_CELL_PIXELS: (usize, usize) = (10, 20);
+
+/// Reads a PNG or JPEG image and builds a logo drawn as sixel graphics, scaled to fill the given cells
+/// The image is scaled to whole cells using the cell size in pixels, which terminals don't report without a round trip
+pub fn sixel_logo(path: &Path, size: CellSize, cell_pixels: (usize, usize)) -> Result<Logo, LogoError> {
+    let image = decode_image(path)?;
+    let (columns, rows) = size.fit(image.width(), image.height());
+    let image = image::imageops::resize(
+        &image,
+        (columns * cell_pixels.0) as u32,
+        (rows * cell_pixels.1) as u32,
+        FilterType::Triangle,
+    );
+    // Sixel images move the cursor below them and can scroll the screen, so room is made first
+    // and the cursor is saved and restored around the image to leave it in the top left cell
+    let escape = format!("{}\x1b[{}A\x1b7{}\x1b8", "\n".repeat(rows), rows, sixel_escape(&image));
+    Ok(Logo::Bitmap { escape, columns, rows })
+}
+
+/// Characters an image is converted to in text logos
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Upper half blocks with the upper pixel as foreground and the lower one as background color, 1x2 pixels per cell
+    Blocks,
+    /// Braille patterns colored with the average of their dots, 2x4 pixels per cell
+    Braille,
+    /// ASCII characters picked by luminance, 1 pixel per cell
+    Ascii,
+}
+
+/// Characters of ASCII logos from the darkest to the brightest
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Reads a PNG or JPEG image and converts it to lines of text with truecolor escapes, which go through the same layout as stock logos
+pub fn text_logo(path: &Path, size: CellSize, style: TextStyle) -> Result<Logo, LogoError> {
+    let image = decode_image(path)?;
+    let (columns, rows) = size.fit(image.width(), image.height());
+    let (cell_width, cell_height) = match style {
+        TextStyle::Blocks => (1, 2),
+        TextStyle::Braille => (2, 4),
+        TextStyle::Ascii => (1, 1),
+    };
+    let image = image::imageops::resize(&image, (columns * cell_width) as u32, (rows * cell_height) as u32, FilterType::Triangle);
+    let lines = match style {
+        TextStyle::Blocks => blocks_lines(&image),
+        TextStyle::Braille => braille_lines(&image),
+        TextStyle::Ascii => ascii_lines(&image),
+    };
+    Ok(Logo::Text(lines))
+}
+
+/// Returns the color of a pixel, or None if it's mostly transparent
+fn opaque_color(image: &RgbaImage, x: u32, y: u32) -> Option<[u8; 3]> {
+    let pixel = image.get_pixel(x, y);
+    (pixel[3] >= 128).then_some([pixel[0], pixel[1], pixel[2]])
+}
+
+/// Perceived brightness of a color from 0 to 255
+fn luminance(color: [u8; 3]) -> f64 {
+    0.2126 * color[0] as f64 + 0.7152 * color[1] as f64 + 0.0722 * color[2] as f64
+}
+
+/// Converts every two rows of pixels into a line of half blocks, transparent halves are left in the terminal's background color
+fn blocks_lines(image: &RgbaImage) -> Vec<String> {
+    (0..image.height() / 2)
+        .map(|row| {
+            let mut line = String::new();
+            for x in 0..image.width() {
+                match (opaque_color(image, x, row * 2), opaque_color(image, x, row * 2 + 1)) {
+                    (Some([r, g, b]), Some([br, bg, bb])) => line.push_str(&format!("\x1b[38;2;{};{};{};48;2;{};{};{}m▀", r, g, b, br, bg, bb)),
+                    (Some([r, g, b]), None) => line.push_str(&format!("\x1b[49;38;2;{};{};{}m▀", r, g, b)),
+                    (None, Some([r, g, b])) => line.push_str(&format!("\x1b[49;38;2;{};{};{}m▄", r, g, b)),
+                    (None, None) => line.push_str("\x1b[49m "),
+                }
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+/// Converts every 2x4 block of pixels into a braille pattern
+/// Dots are set for opaque pixels of images with transparency, and for pixels brighter than average in other images
+fn braille_lines(image: &RgbaImage) -> Vec<String> {
+    let colors: Vec<[u8; 3]> = image.pixels().filter(|pixel| pixel[3] >= 128).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+    let transparent = colors.len() < (image.width() * image.height()) as usize;
+    let average = colors.iter().map(|color| luminance(*color)).sum::<f64>() / colors.len().max(1) as f64;
+    // Bit of every dot in a braille pattern, by column and row, see https://en.wikipedia.org/wiki/Braille_Patterns
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    (0..image.height() / 4)
+        .map(|row| {
+            let mut line = String::new();
+            for column in 0..image.width() / 2 {
+                let mut bits = 0;
+                let mut sums = [0u32; 3];
+                for (dx, dots) in DOTS.iter().enumerate() {
+                    for (dy, bit) in dots.iter().enumerate() {
+                        let Some(color) = opaque_color(image, column * 2 + dx as u32, row * 4 + dy as u32) else {
+                            continue;
+                        };
+                        if transparent || luminance(color) > average {
+                            bits |= bit;
+                            sums = [0, 1, 2].map(|channel| sums[channel] + color[channel] as u32);
+                        }
+                    }
+                }
+                let dot_count = bits.count_ones().max(1);
+                let [r, g, b] = sums.map(|sum| sum / dot_count);
+                let pattern = char::from_u32(0x2800 + bits).unwrap_or(' ');
+                line.push_str(&format!("\x1b[38;2;{};{};{}m{}", r, g, b, pattern));
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+/// Converts every pixel into an ASCII character picked by its luminance, colored with the pixel's color
+fn ascii_lines(image: &RgbaImage) -> Vec<String> {
+    (0..image.height())
+        .map(|y| {
+            let mut line = String::new();
+            for x in 0..image.width() {
+                match opaque_color(image, x, y) {
+                    Some([r, g, b]) => {
+                        let level = (luminance([r, g, b]) / 256.0 * ASCII_RAMP.len() as f64) as usize;
+                        line.push_str(&format!("\x1b[38;2;{};{};{}m{}", r, g, b, ASCII_RAMP[level.min(ASCII_RAMP.len() - 1)] as char));
+                    }
+                    None => line.push(' '),
+                }
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+/// Decodes a PNG or JPEG image file into RGBA pixels
+fn decode_image(path: &Path) -> Result<RgbaImage, LogoError> {
+    let bytes = fs::read(path).map_err(|error| LogoError::Read { path: path.display().to_string(), error })?;
+    image::load_from_memory(&bytes)
+        .map(|image| image.to_rgba8())
+        .map_err(|error| LogoError::Format { path: path.display().to_string(), message: error.to_string() })
+}
+
+/// Encodes an image as a sixel escape with up to 256 colors, pixels that are mostly transparent are left unpainted
+/// Every band of 6 pixel rows is drawn once per color used in it, with repeated columns run-length encoded,
+/// see https://vt100.net/docs/vt3xx-gp/chapter14.html
+pub fn sixel_escape(image: &RgbaImage) -> String {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let opaque: Vec<[u8; 3]> = image.pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let palette = median_cut(opaque, 256);
+
+    // Palette index of every pixel, None for transparent ones
+    let mut nearest_cache: HashMap<[u8; 3], usize> = HashMap::new();
+    let indexes: Vec<Option<usize>> = image.pixels()
+        .map(|pixel| {
+            if pixel[3] < 128 {
+                return None;
+            }
+            let color = [pixel[0], pixel[1], pixel[2]];
+            Some(*nearest_cache.entry(color).or_insert_with(|| nearest_color(&palette, color)))
+        })
+        .collect();
+
+    // P2=1 keeps pixels with no color transparent, raster attributes set 1:1 pixel aspect ratio and the image size
+    let mut escape = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for (index, color) in palette.iter().enumerate() {
+        // Sixel colors are percentages
+        let [red, green, blue] = color.map(|channel| channel as u32 * 100 / 255);
+        escape.push_str(&format!("#{};2;{};{};{}", index, red, green, blue));
+    }
+    for band in (0..height).step_by(6) {
+        let band_height = 6.min(height - band);
+        let mut colors: Vec<usize> = (band..band + band_height)
+            .flat_map(|y| indexes[y * width..(y + 1) * width].iter().flatten().copied())
+            .collect();
+        colors.sort();
+        colors.dedup();
+        for color in colors {
+            escape.push_str(&format!("#{}", color));
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    let bits = (0..band_height)
+                        .filter(|row| indexes[(band + row) * width + x] == Some(color))
+                        .fold(0u8, |bits, row| bits | 1 << row);
+                    b'?' + bits
+                })
+                .collect();
+            push_run_length(&mut escape, &sixels);
+            // Graphics carriage return, the next color is drawn over the same band
+            escape.push('$');
+        }
+        // Graphics new line, moves to the next band
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+/// Appends sixel characters, replacing runs longer than 3 with a repeat introducer like "!12?"
+fn push_run_length(escape: &mut String, sixels: &[u8]) {
+    let mut index = 0;
+    while index < sixels.len() {
+        let run = sixels[index..].iter().take_while(|sixel| **sixel == sixels[index]).count();
+        if run > 3 {
+            escape.push_str(&format!("!{}{}", run, sixels[index] as char));
+        } else {
+            escape.extend(std::iter::repeat_n(sixels[index] as char, run));
+        }
+        index += run;
+    }
+}
+
+/// Reduces colors to a palette of at most `size` colors with the median cut algorithm
+/// The box of colors with the widest channel range is split at the median of that channel, until there are enough boxes
+fn median_cut(colors: Vec<[u8; 3]>, size: usize) -> Vec<[u8; 3]> {
+    if colors.is_empty() {
+        return Vec::new();
+    }
+    let channel_range = |colors: &[[u8; 3]], channel: usize| {
+        let (min, max) = colors.iter().fold((255, 0), |(min, max), color| (min.min(color[channel]), max.max(color[channel])));
+        max - min
+    };
+    let widest_channel = |colors: &[[u8; 3]]| (0..3).max_by_key(|channel| channel_range(colors, *channel)).unwrap_or(0);
+
+    let mut boxes = vec![colors];
+    while boxes.len() < size {
+        let Some((index, _)) = boxes.iter()
+            .enumerate()
+            .map(|(index, colors)| (index, channel_range(colors, widest_channel(colors))))
+            .filter(|(_, range)| *range > 0)
+            .max_by_key(|(_, range)| *range)
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        let channel = widest_channel(&colors);
+        colors.sort_by_key(|color| color[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter()
+        .map(|colors| {
+            let sums = colors.iter().fold([0u64; 3], |sums, color| [0, 1, 2].map(|channel| sums[channel] + color[channel] as u64));
+            sums.map(|sum| (sum / colors.len() as u64) as u8)
+        })
+        .collect()
+}
+
+/// Returns the index of the palette color closest to the given one
+fn nearest_color(palette: &[[u8; 3]], color: [u8; 3]) -> usize {
+    let distance = |other: &[u8; 3]| -> i32 {
+        (0..3).map(|channel| (other[channel] as i32 - color[channel] as i32).pow(2)).sum()
+    };
+    (0..palette.len()).min_by_key(|index| distance(&palette[*index])).unwrap_or(0)
+}
+
+/// Returns width and height of a PNG image from its IHDR chunk, or None if it's not a PNG
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    if !png.starts_with(b"\x89PNG\r\n\x1a\n") || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(png.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+/// Encodes bytes as standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| group | (*byte as u32) << (16 - index * 8));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - index * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn kitty_chunks() {
+        // 7000 bytes are 9336 base64 characters, sent as three chunks
+        let escape = kitty_escape(&[0; 7000], 20, 10);
+        let chunks: Vec<&str> = escape.strip_suffix("\x1b\\").unwrap().split("\x1b\\").collect();
+        assert_eq!(chunks.len(), 3);
+        for (index, chunk) in chunks.iter().enumerate() {
+            let (control, payload) = chunk.strip_prefix("\x1b_G").unwrap().split_once(';').unwrap();
+            assert!(payload.len() <= KITTY_CHUNK_SIZE);
+            let more = if index + 1 < chunks.len() { "m=1" } else { "m=0" };
+            if index == 0 {
+                assert_eq!(control, format!("f=100,a=T,C=1,q=2,c=20,r=10,{}", more));
+            } else {
+                assert_eq!(control, more);
+            }
+        }
+        let payload: String = chunks.iter().map(|chunk| chunk.split_once(';').unwrap().1).collect();
+        assert_eq!(payload, base64(&[0; 7000]));
+    }
+}
@@ -1,19 +1,26 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs::{self, File};
-use std::env;
+use std::path::{Path, PathBuf};
 use sysinfo::{
     System
 };
 use include_dir::{include_dir, Dir};
 use std::io::{self, BufRead, BufReader, Write};
-use serde_json::Value;
 use tokio::sync::mpsc;
 use tokio::task;
 use strip_ansi_escapes::strip_str;
+use unicode_width::UnicodeWidthChar;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 mod os_map;
 mod modules;
+mod render;
+mod config;
+mod sqlite;
+mod logo;
+
+use modules::ModuleResult;
+use logo::{CellSize, Logo, TextStyle};
 
 /// This declaration tells include_dir to include the "assets" directory in the rastfetch binary, and allows modules to use files inside it
 pub static ASSETS: Dir = include_dir!("assets");
@@ -29,6 +36,18 @@ struct Args {
     #[arg(long)]
     config: bool,
 
+    /// Let --config overwrite an existing config, after backing it up
+    #[arg(long, requires = "config")]
+    force: bool,
+
+    /// Print default config to stdout instead of writing it to a file
+    #[arg(long)]
+    print_default_config: bool,
+
+    /// Use this config file instead of searching $XDG_CONFIG_HOME, $XDG_CONFIG_DIRS and /etc/rastfetch
+    #[arg(long, value_name = "PATH")]
+    config_file: Option<PathBuf>,
+
     /// Don't print logo
     #[arg(long, default_value_t = false)]
     nologo: bool,
@@ -36,6 +55,19 @@ struct Args {
     /// Pick color palette
     #[arg(short, long)]
     palette: Option<String>,
+
+    /// Output format, json prints one object per module without logo
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+/// Formats in which the fetched information can be printed
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Colored text next to the logo
+    Text,
+    /// JSON array with one object per module
+    Json,
 }
 
 #[derive(Parser)]
@@ -44,9 +76,42 @@ struct LogoArgs {
     #[arg(short, long)]
     logo: Option<String>,
 
-    /// Add this flag -l/--logo if you want to use cutom logo in .config/rastfetch
+    /// Add this flag -l/--logo if you want to use cutom logo in $XDG_CONFIG_HOME/rastfetch (default .config/rastfetch)
     #[arg(short, long, default_value_t = false)]
     custom: bool,
+
+    /// How the logo is drawn, image logos take a path to the image file in -l/--logo
+    #[arg(long, value_enum, default_value_t = LogoType::Text)]
+    logo_type: LogoType,
+
+    /// Width of an image logo in character cells
+    #[arg(long, value_name = "COLUMNS")]
+    logo_width: Option<usize>,
+
+    /// Height of an image logo in character cells, calculated from the width if not given
+    #[arg(long, value_name = "ROWS")]
+    logo_height: Option<usize>,
+
+    /// Keep the active color between lines of the logo like fastfetch, on by default for stock logos
+    #[arg(long, value_name = "BOOL")]
+    logo_color_carry: Option<bool>,
+}
+
+/// Ways of drawing the logo
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum LogoType {
+    /// Stock or custom text logo with color markers
+    Text,
+    /// PNG image drawn with the kitty graphics protocol
+    Kitty,
+    /// PNG or JPEG image drawn as sixel graphics, eg. in foot, WezTerm or xterm -ti vt340
+    Sixel,
+    /// PNG or JPEG image converted to colored half blocks
+    Blocks,
+    /// PNG or JPEG image converted to colored braille patterns
+    Braille,
+    /// PNG or JPEG image converted to colored ASCII characters
+    Ascii,
 }
 
 
@@ -55,111 +120,136 @@ struct LogoArgs {
 async fn main() {
     let args = Args::parse();
 
-    // Create config directory and default config file
+    if args.print_default_config {
+        io::stdout().write_all(config::default_config().as_bytes()).unwrap();
+        return;
+    }
+
+    // Create config directory and default config file, in the user config dir or at --config-file
     if args.config{
-        let home_dir = env::var("HOME").expect("Unable to find home directory");
-        let path = format!("{}/.config/rastfetch", home_dir);
-        fs::create_dir_all(path).expect("Unable to create config directory");
-        io::stdout().write_all(b"Config directory created\n").unwrap();
-        let config_path = format!("{}/.config/rastfetch/config.json", home_dir);
-        let mut file = fs::File::create(config_path).expect("Unable to create config file");
-        if let Some(doc_file) = ASSETS.get_file("default.json") {
-            let contents = doc_file.contents_utf8().unwrap();
-            file.write_all(contents.as_bytes()).expect("Unable to write to config file");
-        } else {
-            io::stdout().write_all(b"Default config file not found\n").unwrap();
-            return;
+        let written = match &args.config_file {
+            Some(path) => Ok(path.clone()),
+            None => config::require_user_config_dir().map(|dir| dir.join("config.json")),
+        }.and_then(|config_path| {
+            let backup = config::write_default_config(&config_path, args.force)?;
+            Ok((config_path, backup))
+        });
+        match written {
+            Ok((config_path, backup)) => {
+                if let Some(backup) = backup {
+                    println!("Previous config backed up to {}", backup.display());
+                }
+                println!("Default config written to {}", config_path.display());
+            }
+            Err(error) => {
+                eprintln!("rastfetch: {}", error);
+                std::process::exit(1);
+            }
         }
         return;
     }
 
     // Load the config file
-    let modules = get_modules().unwrap();
+    let config = match config::load_config(args.config_file.as_deref()) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("rastfetch: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let modules = config.modules;
 
     let module_functions = modules::get_module_functions();
 
+    // Warn about modules that don't exist instead of silently skipping them
+    for module in &modules {
+        if !module_functions.contains_key(module.module.as_str()) {
+            match config::suggest_module(&module.module, module_functions.keys().copied()) {
+                Some(suggestion) => eprintln!("rastfetch: warning: unknown module \"{}\", did you mean \"{}\"?", module.module, suggestion),
+                None => eprintln!("rastfetch: warning: unknown module \"{}\"", module.module),
+            }
+        }
+    }
+
     let (tx, mut rx) = mpsc::channel(modules.len());
 
     let mut tasks = vec![];
 
     // Create a task for each module present in config file, and send the result to ordered channel
     for (index, module) in modules.iter().enumerate() {
-        if let Some(func) = module_functions.get(module.as_str()).cloned() {
+        if let Some(func) = module_functions.get(module.module.as_str()).cloned() {
             let tx_clone = tx.clone();
+            let module = module.clone();
             let task = task::spawn(async move {
-                let result = (func)().await;
-                // Wyślij wynik wraz z indeksem
+                let key = module.key.clone();
+                let mut result = (func)(module).await;
+                // Key set in config overrides the module's default label
+                if let Some(key) = key {
+                    result.set_key(&key);
+                }
+                // Send the result together with its index
                 tx_clone.send((index, result)).await.unwrap();
             });
             tasks.push(task);
         }
     }
 
-    // Check if user wants to print logo
-    if !args.nologo {
-        let logo = read_logo(&args);
-        let logo_lines: Vec<String> = logo.lines().map(|line| line.to_string()).collect();
-        let mut max_width = 0;
-        // Calculate the width of the logo
-        for line in &logo_lines {
-            let stripped_line = strip_str(line);
-            if count_chars_without_markers(&stripped_line) > max_width {
-                max_width = stripped_line.len();
-            }
-            
-        }
-        // Recieve results from the tasks in the correct order
-        let mut results = vec![String::new(); modules.len()];
-        for _ in tasks {
-            let (index, result) = rx.recv().await.unwrap();
-            results[index] = result;
-        }
+    // Recieve results from the tasks in the correct order
+    let mut results: Vec<Option<ModuleResult>> = vec![None; modules.len()];
+    for _ in tasks {
+        let (index, result) = rx.recv().await.unwrap();
+        results[index] = Some(result);
+    }
 
-        let split_results = split_multiline_strings(results);
+    // Machine-readable output skips the logo and modules without any data, like separator
+    if args.format == OutputFormat::Json {
+        let results: Vec<ModuleResult> = results.into_iter().flatten().collect();
+        println!("{}", render::render_json(&results));
+        return;
+    }
 
-        let output_lines = format_terminal_output(&logo_lines, &split_results, max_width + 3);
-        let binding:&[Color] = &[Color::White];
+    // Render each result with the format string set for its module in config
+    let text_results: Vec<String> = modules.iter().zip(&results)
+        .filter_map(|(module, result)| result.as_ref().map(|result| render::render_text(result, module.format.as_deref())))
+        .collect();
+    let split_results = split_multiline_strings(text_results);
+
+    let binding:&[Color] = &[Color::White];
+
+    // Check if user wants to use custom color palette
+    // If not, get the OS name and use the default color palette
+    let os_color = match args.palette.as_deref() {
+        Some(palette) => {
+            os_map::OS_COLORS.get(palette).unwrap_or(&binding)
+        },
+        None =>  {
+            let os = get_os_id().unwrap_or("Can't find system name".to_string());
+            os_map::OS_COLORS.get(os.as_str()).unwrap_or(&binding)
+        }
+    };
 
-        // Check if user wants to use custom color palette
-        // If not, get the OS name and use the default color palette
-        let os_color = match args.palette.as_deref() {
-            Some(palette) => {
-                os_map::OS_COLORS.get(palette).unwrap_or(&binding)
-            },
-            None =>  {
-                let os = get_os_id().unwrap_or("Can't find system name".to_string());
-                os_map::OS_COLORS.get(os.as_str()).unwrap_or(&binding)
+    // Check if user wants to print logo
+    if !args.nologo {
+        let logo = match args.logo_args.logo_type {
+            LogoType::Text => {
+                let logo_lines: Vec<String> = read_logo(&args).lines().map(|line| line.to_string()).collect();
+                // Stock logos come from fastfetch, custom ones may rely on every line starting with $1
+                let color_carry = args.logo_args.logo_color_carry
+                    .or(config.logo.color_carry)
+                    .unwrap_or(!args.logo_args.custom);
+                Logo::Text(if color_carry { carry_colors(logo_lines) } else { logo_lines })
             }
+            _ => read_image_logo(&args, &config.logo),
         };
-        
+
+        let output_lines = format_terminal_output(&logo, &split_results);
+
         // Print the results with logo
         for line in output_lines {
             print_colored(&line, os_color.to_vec()).unwrap();
         }
     // If user doesn't want to print logo
     }else{
-        let mut results = vec![String::new(); modules.len()];
-        // Recieve results from the tasks in the correct order
-        for _ in tasks {
-            let (index, result) = rx.recv().await.unwrap();
-            results[index] = result;
-        }
-
-        let split_results = split_multiline_strings(results);
-
-        let binding:&[Color] = &[Color::White];
-        // Check if user wants to use custom color palette
-        // If not, get the OS name and use the default color palette
-        let os_color = match args.palette.as_deref() {
-            Some(palette) => {
-                os_map::OS_COLORS.get(palette).unwrap_or(&binding)
-            },
-            None =>  {
-                let os = get_os_id().unwrap_or("Can't find system name".to_string());
-                os_map::OS_COLORS.get(os.as_str()).unwrap_or(&binding)
-            }
-        };
-        
         // Print the results without logo
         for line in split_results {
             print_colored(&line, os_color.to_vec()).unwrap();
@@ -174,11 +264,9 @@ fn get_os_id() -> Option<String> {
     match File::open("/etc/os-release") {
         Ok(file) => {
             let reader = BufReader::new(file);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if line.starts_with("ID=") {
-                        return Some(line["ID=".len()..].trim_matches('"').to_string());
-                    }
+            for line in reader.lines().map_while(Result::ok) {
+                if let Some(id) = line.strip_prefix("ID=") {
+                    return Some(id.trim_matches('"').to_string());
                 }
             }
             None
@@ -195,8 +283,13 @@ fn get_os_id() -> Option<String> {
 fn read_logo(args: &Args) -> String {
     if let Some(logo_value) = args.logo_args.logo.as_deref(){
         if args.logo_args.custom{
-            let home_dir = env::var("HOME").expect("Unable to find home directory");
-            let path = format!("{}/.config/rastfetch/{}", home_dir, logo_value);
+            let path = match config::require_user_config_dir() {
+                Ok(dir) => dir.join(logo_value),
+                Err(error) => {
+                    eprintln!("rastfetch: {}", error);
+                    std::process::exit(1);
+                }
+            };
 
             match fs::read_to_string(path){
                 Ok(contents) => contents.to_string(),
@@ -225,22 +318,89 @@ fn read_logo(args: &Args) -> String {
     }
 }
 
-/// Function to count the number of characters in a string without color markers
-fn count_chars_without_markers(text: &str) -> usize {
-    let mut count = 0;
-    let mut chars = text.chars().peekable();
+/// Function to read an image logo from the path given in -l/--logo, drawn the way picked with --logo-type
+/// Exits with an error if there is no path or the image can't be used, as there is no sensible logo to fall back to
+/// Size is taken from --logo-width and --logo-height, or the "logo" object in config
+fn read_image_logo(args: &Args, logo_config: &config::LogoConfig) -> Logo {
+    let Some(path) = args.logo_args.logo.as_deref() else {
+        eprintln!("rastfetch: --logo-type needs the path to an image in -l/--logo");
+        std::process::exit(1);
+    };
+    let size = CellSize {
+        columns: args.logo_args.logo_width.or(logo_config.width),
+        rows: args.logo_args.logo_height.or(logo_config.height),
+    };
+    let cell_pixels = (
+        logo_config.cell_width.unwrap_or(logo::DEFAULT_CELL_PIXELS.0),
+        logo_config.cell_height.unwrap_or(logo::DEFAULT_CELL_PIXELS.1),
+    );
+    let logo = match args.logo_args.logo_type {
+        LogoType::Kitty => logo::kitty_logo(Path::new(path), size),
+        LogoType::Sixel => logo::sixel_logo(Path::new(path), size, cell_pixels),
+        LogoType::Blocks => logo::text_logo(Path::new(path), size, TextStyle::Blocks),
+        LogoType::Braille => logo::text_logo(Path::new(path), size, TextStyle::Braille),
+        LogoType::Ascii => logo::text_logo(Path::new(path), size, TextStyle::Ascii),
+        LogoType::Text => unreachable!("text logos are read by read_logo"),
+    };
+    logo.unwrap_or_else(|error| {
+        eprintln!("rastfetch: {}", error);
+        std::process::exit(1);
+    })
+}
+
+/// Function to keep the active color between logo lines, like fastfetch does
+/// Every line starts with the last color marker of the lines before it, instead of falling back to $1
+fn carry_colors(logo_lines: Vec<String>) -> Vec<String> {
+    let mut color = String::new();
+    logo_lines.into_iter()
+        .map(|line| {
+            let carried = format!("{}{}", color, line);
+            if let Some(marker) = last_marker(&line) {
+                color = marker;
+            }
+            carried
+        })
+        .collect()
+}
+
+/// Function to find the last color marker in a line, like $2 or $0r
+fn last_marker(line: &str) -> Option<String> {
+    let mut marker = None;
+    let mut chars = line.chars().peekable();
     while let Some(c) = chars.next() {
-        if c == '$' {
-            if let Some(&next_char) = chars.peek() {
-                if next_char.is_ascii_digit() {
-                    chars.next(); // pomijamy cyfrę po $
-                    continue;
+        if c == '$' && let Some(&digit) = chars.peek() && digit.is_ascii_digit() {
+            chars.next();
+            match digit {
+                // A $0 cut off at the end of the line has no color, so the one before it stays active
+                '0' => {
+                    if let Some(letter) = chars.next() {
+                        marker = Some(format!("$0{}", letter));
+                    }
                 }
+                _ => marker = Some(format!("${}", digit)),
             }
         }
-        count += 1;
     }
-    count
+    marker
+}
+
+/// Function to measure how many terminal columns a string takes, without color markers and ANSI escapes
+/// Wide characters like CJK and most emoji take two columns, combining marks and control characters none
+fn display_width(text: &str) -> usize {
+    let stripped = strip_str(text);
+    let mut width = 0;
+    let mut chars = stripped.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek().is_some_and(|next_char| next_char.is_ascii_digit()) {
+            // skip the digit after $, and the color letter after $0
+            if chars.next() == Some('0') {
+                chars.next();
+            }
+            continue;
+        }
+        width += c.width().unwrap_or(0);
+    }
+    width
 }
 
 /// Function for splitting multiline strings in *lines* into separate strings while keeping the same order
@@ -252,9 +412,17 @@ fn split_multiline_strings(lines: Vec<String>) -> Vec<String> {
 }
 
 /// Function for displaying the logo and the fetched information alongside each other properly
-/// Takes two tables of strings, and formats them into one vector of lines to be displayed
+/// Takes the logo and a table of strings, and formats them into one vector of lines to be displayed
 /// Also accounts for any color markers that will not be dislpayed, to make sure results are aligned with each other
-fn format_terminal_output(logo_lines: &[String], results: &[String], img_width: usize) -> Vec<String> {
+fn format_terminal_output(logo: &Logo, results: &[String]) -> Vec<String> {
+    let logo_lines = match logo {
+        Logo::Text(logo_lines) => logo_lines,
+        Logo::Bitmap { escape, columns, rows } => return format_bitmap_output(escape, *columns, *rows, results),
+    };
+    // Calculate the width of the logo
+    let max_width = logo_lines.iter().map(|line| display_width(line)).max().unwrap_or(0);
+    let img_width = max_width + 3;
+
     let longer_length = results.len().max(logo_lines.len());
     let mut final_vector = Vec::new();
 
@@ -264,16 +432,9 @@ fn format_terminal_output(logo_lines: &[String], results: &[String], img_width:
         let logo_line = logo_lines.get(i).map(|s| s.as_str()).unwrap_or("");
         let result_line = results.get(i).map(|s| s.as_str()).unwrap_or("");
 
-        // Liczenie rzeczywistej szerokości logo bez znaczników
-        let actual_logo_width = count_chars_without_markers(logo_line);
-        let mut prefix = " ".repeat(img_width);
-
-        // Wstaw logo_line na początek prefixu (lub jego część)
-        if !logo_line.is_empty() {
-            prefix.replace_range(0..actual_logo_width.min(img_width), logo_line);
-        }
-
-        final_string.push_str(&prefix);
+        // Pad the logo line to the same width on screen, markers and escapes in it take no space
+        final_string.push_str(logo_line);
+        final_string.push_str(&" ".repeat(img_width.saturating_sub(display_width(logo_line))));
         final_string.push_str(result_line);
 
         final_vector.push(final_string);
@@ -282,13 +443,30 @@ fn format_terminal_output(logo_lines: &[String], results: &[String], img_width:
     final_vector
 }
 
+/// Reserves a block of cells for an image logo, the image is drawn first and every result line moves the cursor past it
+/// Lines are printed at least as many times as the image has rows, so the prompt ends up below the image
+fn format_bitmap_output(escape: &str, columns: usize, rows: usize, results: &[String]) -> Vec<String> {
+    (0..rows.max(results.len()))
+        .map(|i| {
+            let mut line = String::new();
+            if i == 0 {
+                line.push_str(escape);
+            }
+            if let Some(result) = results.get(i) {
+                line.push_str(&format!("\x1b[{}C{}", columns + 3, result));
+            }
+            line
+        })
+        .collect()
+}
+
 /// Function for finally printing the output into terminal, using the selected color palette
 fn print_colored(text: &str, colors: Vec<Color>) -> io::Result<()> {
     let mut stdout = StandardStream::stdout(ColorChoice::Auto);
     let mut color_spec = ColorSpec::new();
     
     // Ustawienie domyślnego koloru (pierwszy element wektora)
-    if let Some(default_color) = colors.get(0) {
+    if let Some(default_color) = colors.first() {
         color_spec.set_fg(Some(*default_color));
         stdout.set_color(&color_spec)?;
     }
@@ -297,24 +475,24 @@ fn print_colored(text: &str, colors: Vec<Color>) -> io::Result<()> {
     let mut chars = text.chars().peekable();
 
     while let Some(c) = chars.next() {
-        if c == '$' {
-            // Sprawdzenie, czy następny znak to cyfra
-            if let Some(&next_char) = chars.peek() {
-                if next_char.is_ascii_digit() {
-                    chars.next();
-                    let index = next_char.to_digit(10).unwrap() as usize - 1;
-                    if let Some(color) = colors.get(index) {
-                        // Wydrukowanie zbuforowanego tekstu z poprzednim kolorem
-                        write!(stdout, "{}", buffer)?;
-                        buffer.clear();
-                        // Ustawienie nowego koloru
-                        stdout.flush()?;
-                        color_spec.set_fg(Some(*color));
-                        stdout.set_color(&color_spec)?;
-                    }
-                    continue;
-                }
+        // Check if the next character is a digit
+        if c == '$' && let Some(&next_char) = chars.peek() && next_char.is_ascii_digit() {
+            chars.next();
+            // $0 is followed by a letter picking a color that doesn't depend on the palette, eg. $0r for warnings
+            let color = match next_char {
+                '0' => chars.next().and_then(fixed_color),
+                _ => colors.get(next_char.to_digit(10).unwrap() as usize - 1).copied(),
+            };
+            if let Some(color) = color {
+                // Print the buffered text with the previous color
+                write!(stdout, "{}", buffer)?;
+                buffer.clear();
+                // Set the new color
+                stdout.flush()?;
+                color_spec.set_fg(Some(color));
+                stdout.set_color(&color_spec)?;
             }
+            continue;
         }
         buffer.push(c);
     }
@@ -322,28 +500,54 @@ fn print_colored(text: &str, colors: Vec<Color>) -> io::Result<()> {
     // Wydrukowanie pozostałego tekstu
     write!(stdout, "{}", buffer)?;
     stdout.reset()?;
-    write!(stdout, "\n")?;
+    writeln!(stdout)?;
     stdout.flush()?;
     Ok(())
 }
 
-/// Returns config file in a serde_json readable format
-fn read_config() -> Result<Value, Box<dyn std::error::Error>> {
-    let config_path = format!("{}/.config/rastfetch/config.json", env::var("HOME")?);
-    let config_data = fs::read_to_string(config_path)?;
-    let config: Value = serde_json::from_str(&config_data)?;
-    Ok(config)
+/// Colors of $0 markers, named by their first letter
+fn fixed_color(letter: char) -> Option<Color> {
+    match letter {
+        'r' => Some(Color::Red),
+        'g' => Some(Color::Green),
+        'y' => Some(Color::Yellow),
+        'b' => Some(Color::Blue),
+        'm' => Some(Color::Magenta),
+        'c' => Some(Color::Cyan),
+        'w' => Some(Color::White),
+        _ => None,
+    }
 }
 
-/// Returns a string of all modules present in the config file
-fn get_modules() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let config = read_config()?;
-    if let Some(modules) = config["modules"].as_array() {
-        let module_list = modules.iter()
-            .filter_map(|m| m.as_str().map(|s| s.to_string()))
-            .collect();
-        Ok(module_list)
-    } else {
-        Err("Nie znaleziono klucza 'modules' lub nie jest to tablica.".into())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitmap_output() {
+        let results = vec!["$3OS: $2Debian".to_string(), "$3Kernel: $2Linux".to_string()];
+        // The image is taller than the results, so empty lines reserve its rows
+        let lines = format_bitmap_output("<image>", 20, 4, &results);
+        assert_eq!(lines, ["<image>\x1b[23C$3OS: $2Debian", "\x1b[23C$3Kernel: $2Linux", "", ""]);
+        // More results than rows continue below the image
+        let lines = format_bitmap_output("<image>", 5, 1, &results);
+        assert_eq!(lines, ["<image>\x1b[8C$3OS: $2Debian", "\x1b[8C$3Kernel: $2Linux"]);
+    }
+
+    #[test]
+    fn logo_width() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("┌──┐"), 4);
+        assert_eq!(display_width("$1ab$0rc$2"), 3);
+        assert_eq!(display_width("\x1b[38;2;255;0;0m▀▀\x1b[0m"), 2);
+        assert_eq!(display_width("$3│\x1b[1m漢字\x1b[0m$0y│"), 6);
+    }
+
+    #[test]
+    fn carried_colors() {
+        let lines = ["$1ab$2c", "de", "$0rf", "g$0", "h"].map(str::to_string).to_vec();
+        assert_eq!(carry_colors(lines), ["$1ab$2c", "$2de", "$2$0rf", "$0rg$0", "$0rh"]);
+        assert_eq!(last_marker("$0r text $0"), Some("$0r".to_string()));
+        assert_eq!(last_marker("no markers, $ 5"), None);
     }
 }
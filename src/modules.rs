@@ -1,44 +1,136 @@
-//! This module contains all the asynchronous functions for fetching information, and a HashMap binding module names to these functions
+//! This module contains all the asynchronous functions for fetching information, the typed ModuleResult they return, and a HashMap binding module names to these functions
 use sysinfo::{
-    CpuRefreshKind, Disk, Disks, MemoryRefreshKind, RefreshKind, System
+    CpuRefreshKind, Disk, Disks, MemoryRefreshKind, Networks, RefreshKind, System
 };
+use serde::Serialize;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::{env, fs};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::os::unix::fs::MetadataExt;
 use std::pin::Pin;
 use std::future::Future;
 use std::sync::Arc;
-use std::path::Path;
-use whoami;
+use std::time::Duration;
+use std::path::{Path, PathBuf};
 
-use crate::{os_map, ASSETS};
+use crate::{os_map, sqlite};
+use crate::config::ModuleConfig;
 
-type ModuleFunction = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = String> + Send>> + Send + Sync>;
+/// Future resolving to the result of a single module
+pub type ModuleFuture = Pin<Box<dyn Future<Output = ModuleResult> + Send>>;
+/// Function creating a future that fetches one module's information, using the module's settings from config
+pub type ModuleFunction = Arc<dyn Fn(ModuleConfig) -> ModuleFuture + Send + Sync>;
+
+/// Typed data collected by a single module, without any colors, units or markup
+/// Turning it into text or JSON is the job of the `render` module
+#[derive(Serialize, Clone, Debug)]
+pub struct ModuleResult {
+    /// Name of the module as used in config file
+    #[serde(rename = "type")]
+    pub module: &'static str,
+    /// Label displayed in front of the value, eg. "Kernel"
+    pub key: String,
+    /// Structured values fetched by the module, in display order
+    pub result: Map<String, Value>,
+    /// Sub-entries for modules reporting several items, eg. one per disk
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<ModuleResult>,
+    /// Set when the module couldn't fetch its information
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ModuleResult {
+    pub fn new(module: &'static str, key: &str) -> Self {
+        ModuleResult { module, key: key.to_string(), result: Map::new(), entries: Vec::new(), error: None }
+    }
+
+    /// Creates a result for a module that failed to fetch its information
+    pub fn failed(module: &'static str, key: &str, error: impl Into<String>) -> Self {
+        let mut result = ModuleResult::new(module, key);
+        result.error = Some(error.into());
+        result
+    }
+
+    /// Adds a named value to the result
+    pub fn with(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.result.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Adds a sub-entry to the result
+    pub fn with_entry(mut self, entry: ModuleResult) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Replaces the label of the result and all of its sub-entries
+    pub fn set_key(&mut self, key: &str) {
+        self.key = key.to_string();
+        for entry in &mut self.entries {
+            entry.set_key(key);
+        }
+    }
+
+    /// Returns true if the module has nothing to show in machine-readable output
+    pub fn is_empty(&self) -> bool {
+        self.result.is_empty() && self.entries.is_empty() && self.error.is_none()
+    }
+
+    pub fn str_field(&self, name: &str) -> &str {
+        self.result.get(name).and_then(Value::as_str).unwrap_or("Unknown")
+    }
+
+    pub fn u64_field(&self, name: &str) -> u64 {
+        self.result.get(name).and_then(Value::as_u64).unwrap_or(0)
+    }
+
+    pub fn f64_field(&self, name: &str) -> f64 {
+        self.result.get(name).and_then(Value::as_f64).unwrap_or(0.0)
+    }
+}
+
+fn percent_of(used: u64, total: u64) -> u64 {
+    ((used as f64 / total as f64) * 100.0) as u64
+}
 
 /// Hash map mapping functions for fetching information asynchronously to strings present in config file
 pub fn get_module_functions() -> HashMap<&'static str, ModuleFunction> {
-        // Map of module names to their respective functions
-    let mut module_functions: HashMap<&str, Arc<dyn Fn() -> Pin<Box<dyn Future<Output = String> + Send>> + Send + Sync>> = HashMap::new();
-
-    // Wstawianie funkcji do mapy, opakowanych w Box::pin
-    module_functions.insert("title", Arc::new(|| Box::pin(fetch_title())));
-    module_functions.insert("separator", Arc::new(|| Box::pin(fetch_separator())));
-    module_functions.insert("os", Arc::new(|| Box::pin(fetch_os())));
-    module_functions.insert("kernel", Arc::new(|| Box::pin(fetch_kernel())));
-    module_functions.insert("uptime", Arc::new(|| Box::pin(fetch_uptime())));
-    module_functions.insert("memory", Arc::new(|| Box::pin(fetch_memory())));
-    module_functions.insert("shell", Arc::new(|| Box::pin(fetch_shell())));
-    module_functions.insert("cpu", Arc::new(|| Box::pin(fetch_cpu())));
-    module_functions.insert("cpu_usage", Arc::new(|| Box::pin(fetch_cpu_usage())));
-    module_functions.insert("swap", Arc::new(|| Box::pin(fetch_swap())));
-    module_functions.insert("disks", Arc::new(|| Box::pin(fetch_disks())));
-    module_functions.insert("terminal", Arc::new(|| Box::pin(fetch_terminal_emulator())));
-    module_functions.insert("colors", Arc::new(|| Box::pin(fetch_color_palette())));
-    module_functions.insert("bios", Arc::new(|| Box::pin(fetch_bios())));
-    module_functions.insert("editor", Arc::new(|| Box::pin(fetch_editor())));
-    module_functions.insert("platform", Arc::new(|| Box::pin(fetch_platform())));
-    module_functions.insert("chassis", Arc::new(|| Box::pin(fetch_chassis())));
+    // Map of module names to their respective functions
+    let mut module_functions: HashMap<&str, ModuleFunction> = HashMap::new();
+
+    // Insert functions into the map, wrapped in Box::pin
+    module_functions.insert("title", Arc::new(|_| Box::pin(fetch_title())));
+    module_functions.insert("separator", Arc::new(|_| Box::pin(fetch_separator())));
+    module_functions.insert("os", Arc::new(|_| Box::pin(fetch_os())));
+    module_functions.insert("kernel", Arc::new(|_| Box::pin(fetch_kernel())));
+    module_functions.insert("uptime", Arc::new(|_| Box::pin(fetch_uptime())));
+    module_functions.insert("memory", Arc::new(|_| Box::pin(fetch_memory())));
+    module_functions.insert("shell", Arc::new(|config| Box::pin(fetch_shell(config))));
+    module_functions.insert("cpu", Arc::new(|config| Box::pin(fetch_cpu(config))));
+    module_functions.insert("cpu_usage", Arc::new(|config| Box::pin(fetch_cpu_usage(config))));
+    module_functions.insert("swap", Arc::new(|_| Box::pin(fetch_swap())));
+    module_functions.insert("disks", Arc::new(|config| Box::pin(fetch_disks(config))));
+    module_functions.insert("terminal", Arc::new(|_| Box::pin(fetch_terminal_emulator())));
+    module_functions.insert("colors", Arc::new(|_| Box::pin(fetch_color_palette())));
+    module_functions.insert("bios", Arc::new(|_| Box::pin(fetch_bios())));
+    module_functions.insert("editor", Arc::new(|_| Box::pin(fetch_editor())));
+    module_functions.insert("platform", Arc::new(|_| Box::pin(fetch_platform())));
+    module_functions.insert("chassis", Arc::new(|_| Box::pin(fetch_chassis())));
+    module_functions.insert("host", Arc::new(|_| Box::pin(fetch_host())));
+    module_functions.insert("board", Arc::new(|_| Box::pin(fetch_board())));
+    module_functions.insert("virtualization", Arc::new(|_| Box::pin(fetch_virtualization())));
+    module_functions.insert("gpu", Arc::new(|config| Box::pin(fetch_gpu(config))));
+    module_functions.insert("packages", Arc::new(|_| Box::pin(fetch_packages())));
+    module_functions.insert("de", Arc::new(|_| Box::pin(fetch_desktop_environment())));
+    module_functions.insert("wm", Arc::new(|_| Box::pin(fetch_window_manager())));
+    module_functions.insert("battery", Arc::new(|_| Box::pin(fetch_battery())));
+    module_functions.insert("display", Arc::new(|_| Box::pin(fetch_display())));
+    module_functions.insert("network", Arc::new(|config| Box::pin(fetch_network(config, "network", "Network"))));
+    module_functions.insert("localip", Arc::new(|config| Box::pin(fetch_network(config, "localip", "Local IP"))));
+    module_functions.insert("sensors", Arc::new(|config| Box::pin(fetch_sensors(config))));
 
 
     module_functions
@@ -46,169 +138,445 @@ pub fn get_module_functions() -> HashMap<&'static str, ModuleFunction> {
 
 /// Title is in (username)@(hostname) format
 /// Data fetched from environment variables
-async fn fetch_title() -> String {
-    let user = env::var("USER");
-    let hostname = env::var("HOSTNAME");
-    let title = format!("\n$1{}$2@$1{}$2", user.unwrap_or(format!("{}", "Unknown")), hostname.unwrap_or(format!("{}", "Unknown")));
-    title
+async fn fetch_title() -> ModuleResult {
+    let user = env::var("USER").unwrap_or("Unknown".to_string());
+    let hostname = env::var("HOSTNAME").unwrap_or("Unknown".to_string());
+    ModuleResult::new("title", "Title")
+        .with("user", user)
+        .with("hostname", hostname)
 }
 /// Just prints a separator
-async fn fetch_separator() -> String {
-    let separator = "$2-----------$1";
-    separator.to_string()
+async fn fetch_separator() -> ModuleResult {
+    ModuleResult::new("separator", "Separator")
 }
 /// Fetches disto in a pretty format using whoami
-async fn fetch_os() -> String {
-    let os = whoami::distro();
-    let os_string = format!("$3OS:$2 {}", os);
-    os_string
+async fn fetch_os() -> ModuleResult {
+    ModuleResult::new("os", "OS").with("name", whoami::distro())
 }
 /// Fetches kernel name and version from /proc/version
-async fn fetch_kernel() -> String {
-    let kernel_string: String = match fs::read_to_string("/proc/version") {
-        Ok(content) => {
-            let kernel = {
-                let parts: Vec<&str> = content.split_whitespace().collect();
-                (parts[0].to_string(), parts[2].to_string())
-            };
-            format!("$3Kernel:$2 {} v{}", kernel.0, kernel.1)
-        }
-        Err(_) => {
-            let kernel_version = System::kernel_long_version();
-            format!("$3Kernel:$2 {}", kernel_version)
-        }
+async fn fetch_kernel() -> ModuleResult {
+    let proc_version = fs::read_to_string("/proc/version").ok();
+    let parts: Vec<&str> = proc_version.as_deref().unwrap_or("").split_whitespace().collect();
+    let (name, version) = match parts.as_slice() {
+        [name, _, version, ..] => (name.to_string(), version.to_string()),
+        _ => match (System::name(), System::kernel_version()) {
+            (Some(name), Some(version)) => (name, version),
+            _ => return ModuleResult::failed("kernel", "Kernel", "Unable to detect kernel"),
+        },
     };
-    kernel_string
+    ModuleResult::new("kernel", "Kernel")
+        .with("name", name)
+        .with("version", version)
 }
 /// Fetches uptime using sysinfo
-async fn fetch_uptime() -> String {
-    let uptime = System::uptime();
-    let uptime_string = format!("$3Uptime:$2 {}", format_time(uptime));
-    uptime_string
-}
-/// Formats uptime in a pretty way
-fn format_time(seconds: u64) -> String {
-    let hours = seconds / 3600;
-    let seconds_left_after_hours = seconds % 3600;
-    let minutes = seconds_left_after_hours / 60;
-    let seconds = seconds_left_after_hours % 60;
-
-    let mut parts = Vec::new();
-
-    if hours > 0 {
-        parts.push(format!("$2{}$3h$2", hours));
-    }
-    if minutes > 0 || hours > 0 {
-        parts.push(format!("$2{}$3m$2", minutes));
-    }
-    parts.push(format!("$2{}$3s$2", seconds));
-
-    parts.join(" ")
+async fn fetch_uptime() -> ModuleResult {
+    ModuleResult::new("uptime", "Uptime").with("total_seconds", System::uptime())
 }
 /// Fetches used_memory/total_memory - (used)% using sysinfo
-async fn fetch_memory() -> String {
+async fn fetch_memory() -> ModuleResult {
     let sys = System::new_with_specifics(
         RefreshKind::nothing().with_memory(MemoryRefreshKind::everything()),
     );
     let total_memory = sys.total_memory();
     let used_memory = sys.used_memory();
-    let used_percentage = (used_memory as f64 / total_memory as f64) * 100.0;
-    let memory_string = format!(
-        "$3Memory Used: $2{:.2} $3GiB / $2{:.2} $3GiB $4({}%)$2",
-        used_memory as f64 / 1073741824.0,
-        total_memory as f64 / 1073741824.0,
-        used_percentage as u8
-    );
-    memory_string
+    ModuleResult::new("memory", "Memory Used")
+        .with("used_bytes", used_memory)
+        .with("total_bytes", total_memory)
+        .with("percent", percent_of(used_memory, total_memory))
 }
-/// Fetches shell from the SHELL env variable
-async fn fetch_shell() -> String {
-    if let Ok(shell) = std::env::var("SHELL") {
-        let shell_name = shell.split('/').last().unwrap_or("Unknown");
-        return format!("$3Shell:$2 {}", shell_name)
-    } else {
-        "$3Shell:$2 Unknown".to_string()
-    }
-}
-
-/* This function works great, if you don't care about speed.
-    * fething shell version via command line is very slow, so for now no version
-async fn fetch_shell() -> String {
-    if let Ok(shell) = std::env::var("SHELL") {
-        let shell_name = shell.split('/').last().unwrap_or("Unknown");
-        let shell_command = os_map::SHELL_VERSIONS.get(shell_name).unwrap_or(&"");
-        if shell_command.is_empty() {
-            return format!("$3Shell:$2 {}", shell_name)
-        }
-        match Command::new(&shell)
-            .arg("-c")
-            .arg(shell_command)
-            .output()
-        {
-            Ok(output) => {
-                let shell_version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                return format!("$3Shell:$2 {} {}", shell_name, shell_version);
+/// Fetches the shell rastfetch was started from, by finding the closest shell among parent processes
+/// Falls back to the SHELL env variable if there is no /proc or no shell among them
+/// Version is read from the shell binary or the package database, options: "subprocess" - ask the shell itself as a last resort, slow (default false)
+async fn fetch_shell(config: ModuleConfig) -> ModuleResult {
+    let (shell_name, shell_path) = match shell_from_process_tree(Path::new("/proc"), std::process::id()) {
+        Some((pid, name)) => (name, fs::read_link(format!("/proc/{}/exe", pid)).ok()),
+        None => {
+            let shell = env::var("SHELL").unwrap_or_default();
+            match shell.rsplit('/').next().filter(|name| !name.is_empty()) {
+                Some(name) => (name.to_string(), Some(PathBuf::from(&shell))),
+                None => return ModuleResult::failed("shell", "Shell", "Unknown"),
             }
-            Err(_) => return format!("$3Shell:$2 {} Unknown", shell_name),
         }
-    } else {
-        "$3Shell:$2 Unknown".to_string()
+    };
+    let mut version = shell_path.as_deref().and_then(|path| shell_version_from_binary(&shell_name, path));
+    if version.is_none() {
+        // Package is named after the binary, so "sh" is looked up as the shell it points to, eg. dash
+        let package = shell_path.as_deref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(shell_name.clone());
+        version = package_version(&package);
+    }
+    if version.is_none() && config.bool_option("subprocess", false) && let Some(path) = &shell_path {
+        version = shell_version_from_subprocess(&shell_name, path).await;
+    }
+
+    let result = ModuleResult::new("shell", "Shell").with("name", shell_name);
+    match version {
+        Some(version) => result.with("version", version),
+        None => result,
     }
 }
-*/
-/// Fetches cpu information using sysinfo
-async fn fetch_cpu() -> String {
+
+/// Returns pid and name of the closest process above `pid` that is a shell
+/// Other processes on the way, eg. sudo, timeout or make, are walked past until reaching init
+fn shell_from_process_tree(proc_dir: &Path, mut pid: u32) -> Option<(u32, String)> {
+    // Limit the walk in case of a loop, eg. when pids are reused
+    for _ in 0..64 {
+        pid = parent_pid(proc_dir, pid).filter(|parent| *parent > 1)?;
+        let name = process_name(proc_dir, pid)?;
+        if os_map::SHELLS.contains(name.as_str()) {
+            return Some((pid, name));
+        }
+        if name == "systemd" || name == "init" {
+            return None;
+        }
+    }
+    None
+}
+
+/// Finds the version string a shell embeds in its binary, currently only bash has a reliable one: "@(#)Bash version 5.2.15(1) release"
+fn shell_version_from_binary(shell_name: &str, path: &Path) -> Option<String> {
+    let marker: &[u8] = match shell_name {
+        "bash" => b"@(#)Bash version ",
+        _ => return None,
+    };
+    let binary = fs::read(path).ok()?;
+    let start = binary.windows(marker.len()).position(|window| window == marker)? + marker.len();
+    let version: String = binary[start..].iter()
+        .take_while(|byte| byte.is_ascii_digit() || **byte == b'.')
+        .map(|byte| *byte as char)
+        .collect();
+    Some(version).filter(|version| !version.is_empty())
+}
+
+/// Looks up the upstream version of an installed package in dpkg, pacman or apk databases
+fn package_version(package: &str) -> Option<String> {
+    // dpkg: paragraphs with "Package:", "Status:" and "Version:" lines, version like 1:5.2.15-2+b8
+    if let Ok(status) = fs::read_to_string("/var/lib/dpkg/status") {
+        let package_line = format!("Package: {}", package);
+        let version = status.split("\n\n")
+            .find(|paragraph| paragraph.lines().any(|line| line == package_line)
+                && paragraph.lines().any(|line| line == "Status: install ok installed"))
+            .and_then(|paragraph| paragraph.lines().find_map(|line| line.strip_prefix("Version: ")));
+        if let Some(version) = version {
+            let version = version.split_once(':').map_or(version, |(_, version)| version);
+            let version = version.rsplit_once('-').map_or(version, |(version, _)| version);
+            return Some(version.split('+').next().unwrap_or(version).to_string());
+        }
+    }
+    // pacman: one directory per package, named like bash-5.2.026-2
+    if let Ok(entries) = fs::read_dir("/var/lib/pacman/local") {
+        let prefix = format!("{}-", package);
+        let version = entries.map_while(Result::ok).find_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (version, release) = name.strip_prefix(&prefix)?.rsplit_once('-')?;
+            (!version.contains('-') && release.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.'))
+                .then(|| version.split_once(':').map_or(version, |(_, version)| version).to_string())
+        });
+        if version.is_some() {
+            return version;
+        }
+    }
+    // apk: paragraphs with "P:" name and "V:" version lines, version like 5.2.21-r0
+    if let Ok(installed) = fs::read_to_string("/lib/apk/db/installed") {
+        let package_line = format!("P:{}", package);
+        let version = installed.split("\n\n")
+            .find(|paragraph| paragraph.lines().any(|line| line == package_line))
+            .and_then(|paragraph| paragraph.lines().find_map(|line| line.strip_prefix("V:")));
+        if let Some(version) = version {
+            return Some(version.rsplit_once("-r").map_or(version, |(version, _)| version).to_string());
+        }
+    }
+    None
+}
+
+/// Asks the shell for its version by running it, only used when enabled in config because it's slow
+async fn shell_version_from_subprocess(shell_name: &str, path: &Path) -> Option<String> {
+    let command = os_map::SHELL_VERSIONS.get(shell_name)?;
+    let output = tokio::process::Command::new(path)
+        .arg("-c")
+        .arg(command)
+        .output()
+        .await
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(version).filter(|version| !version.is_empty())
+}
+/// Fetches cpu model from /proc/cpuinfo, topology, frequencies and caches from /sys/devices/system/cpu,
+/// and its temperature from hwmon or thermal zones. Falls back to sysinfo where sysfs has nothing, eg. in some VMs
+/// Options: "show_cores" - show physical cores and threads (default true), "show_frequency" - show max frequency (default true),
+/// "show_cache" - show L1, L2 and L3 cache sizes (default false), "temperature" - show the temperature (default true),
+/// "high" and "critical" - same as for sensors
+async fn fetch_cpu(config: ModuleConfig) -> ModuleResult {
     let sys = System::new_with_specifics(
         RefreshKind::nothing().with_cpu(CpuRefreshKind::everything())
     );
-    
-    let cpus = sys.cpus();
-    let cores = cpus.len();
-    let cpu = &cpus[0];
-    let mut buffer = String::new();
-    let brand = cpu.brand();
-    let name = cpu.name();
-    let freq = cpu.frequency();
-    let final_str = format!("$3CPU: $2{} {} {}$3core $2{}$3Hz$2", brand, name, cores, freq);
-    buffer.push_str(&final_str);
-    buffer
-}
-
-/// For now this function is not used, because it is slow.
-/// Fetches CPU usage in percentage using sysinfo
-async fn fetch_cpu_usage() -> String {
-    let mut s = System::new_with_specifics(
-        RefreshKind::nothing().with_cpu(CpuRefreshKind::everything()),
-    );
-    // Wait a bit because CPU usage is based on diff.
-    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
-    // Refresh CPUs again to get actual value.
-    s.refresh_cpu_all();
-    let cpus = s.cpus();
-    let cpu = &cpus[0];
-    let cpu_usage = cpu.cpu_usage();
-    format!( "$3CPU Usage: $4{:.2}%$2", cpu_usage)
+    let Some(cpu) = sys.cpus().first() else {
+        return ModuleResult::failed("cpu", "CPU", "No CPU found");
+    };
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let cpuinfo_field = |name: &str| {
+        cpuinfo.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    // ARM kernels only have "model name" on some SoCs, sysinfo then builds the brand from the implementer
+    let brand = cpuinfo_field("model name").unwrap_or(cpu.brand().to_string());
+    let topology = read_cpu_topology(Path::new("/sys/devices"));
+
+    let mut result = ModuleResult::new("cpu", "CPU")
+        .with("name", clean_cpu_name(&brand))
+        .with("brand", brand);
+    if let Some(vendor) = cpuinfo_field("vendor_id") {
+        result = result.with("vendor", vendor);
+    }
+    if topology.packages > 1 {
+        result = result.with("packages", topology.packages);
+    }
+    if config.bool_option("show_cores", true) {
+        result = result
+            .with("cores", topology.cores.max(1))
+            .with("threads", topology.threads.max(sys.cpus().len()));
+        if let Some((performance, efficiency)) = topology.hybrid {
+            result = result.with("performance_cores", performance).with("efficiency_cores", efficiency);
+        }
+    }
+    if config.bool_option("show_frequency", true) {
+        if let Some(base) = topology.base_frequency_mhz {
+            result = result.with("base_frequency_mhz", base);
+        }
+        if let Some(max) = topology.max_frequency_mhz {
+            result = result.with("max_frequency_mhz", max);
+        }
+        // Max frequency is what the CPU is advertised with, current frequency of one core changes all the time
+        result = result.with("frequency_mhz", topology.max_frequency_mhz.unwrap_or(cpu.frequency()));
+    }
+    if config.bool_option("show_cache", false) {
+        for (level, bytes) in topology.cache_bytes.iter().enumerate().filter(|(_, bytes)| **bytes > 0) {
+            result = result.with(&format!("l{}_bytes", level + 1), *bytes);
+        }
+    }
+    if !config.bool_option("temperature", true) {
+        return result;
+    }
+    match cpu_temperature(Path::new("/sys/class/hwmon"), Path::new("/sys/class/thermal")) {
+        Some(celsius) => with_temperature(result, celsius, &Thresholds::from_config(&config)),
+        None => result,
+    }
+}
+
+/// Removes trademarks and the frequency from the CPU brand, eg. "Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz" becomes "Intel Core i7-8700"
+fn clean_cpu_name(brand: &str) -> String {
+    let name = brand.split(" @ ").next().unwrap_or(brand);
+    let name = name.replace("(R)", "").replace("(TM)", "").replace("(tm)", "");
+    let words: Vec<&str> = name.split_whitespace()
+        .filter(|word| !matches!(*word, "CPU" | "Processor" | "processor"))
+        .collect();
+    // Generic words are kept if only the vendor would be left, eg. "AMD Processor"
+    if words.len() < 2 {
+        return name.split_whitespace().collect::<Vec<&str>>().join(" ");
+    }
+    words.join(" ")
+}
+
+/// CPU topology summed over all online CPUs
+#[derive(Default)]
+struct CpuTopology {
+    packages: usize,
+    cores: usize,
+    threads: usize,
+    /// Physical performance and efficiency cores of hybrid CPUs
+    hybrid: Option<(usize, usize)>,
+    base_frequency_mhz: Option<u64>,
+    max_frequency_mhz: Option<u64>,
+    /// Total size of L1 (data and instruction), L2 and L3 caches
+    cache_bytes: [u64; 3],
+}
+
+/// Reads topology, frequencies and caches of every CPU in <devices_dir>/system/cpu
+/// Hybrid Intel CPUs list their P-cores and E-cores in <devices_dir>/cpu_core and cpu_atom, other hybrid CPUs like ARM big.LITTLE
+/// are split by cpu_capacity, where the cores with the highest capacity are the performance ones
+fn read_cpu_topology(devices_dir: &Path) -> CpuTopology {
+    let cpus: Vec<(u32, PathBuf)> = numbered_entries(&devices_dir.join("system/cpu"), "cpu", "")
+        .into_iter()
+        .filter(|cpu| cpu.join("topology").exists())
+        .filter_map(|cpu| Some((cpu.file_name()?.to_string_lossy().strip_prefix("cpu")?.parse().ok()?, cpu)))
+        .collect();
+    let mut topology = CpuTopology { threads: cpus.len(), ..Default::default() };
+
+    let mut packages = Vec::new();
+    // Threads of one core share the core_cpus_list, called thread_siblings_list in older kernels
+    let mut cores: Vec<(u32, String)> = Vec::new();
+    let mut caches = Vec::new();
+    for (index, cpu) in &cpus {
+        let topology_dir = cpu.join("topology");
+        let package = read_sysfs(&topology_dir, "physical_package_id").unwrap_or_default();
+        if !packages.contains(&package) {
+            packages.push(package);
+        }
+        if let Some(core) = read_sysfs(&topology_dir, "core_cpus_list").or_else(|| read_sysfs(&topology_dir, "thread_siblings_list")) {
+            cores.push((*index, core));
+        }
+
+        let cpufreq = cpu.join("cpufreq");
+        // Frequencies are in kHz, hybrid CPUs have different ones per core type so the highest is used
+        if let Some(max) = read_sysfs_u64(&cpufreq, "cpuinfo_max_freq").map(|khz| khz / 1000) {
+            topology.max_frequency_mhz = topology.max_frequency_mhz.max(Some(max));
+        }
+        if let Some(base) = read_sysfs_u64(&cpufreq, "base_frequency").map(|khz| khz / 1000) {
+            topology.base_frequency_mhz = topology.base_frequency_mhz.max(Some(base));
+        }
+
+        // Caches shared by several CPUs are listed under each of them, they are told apart by the CPUs sharing them
+        for cache in numbered_entries(&cpu.join("cache"), "index", "") {
+            let (Some(level), Some(size)) = (read_sysfs_u64(&cache, "level"), read_sysfs(&cache, "size")) else {
+                continue;
+            };
+            let id = (level, read_sysfs(&cache, "type"), read_sysfs(&cache, "shared_cpu_list").unwrap_or(index.to_string()));
+            if (1..=3).contains(&level) && !caches.contains(&id) {
+                caches.push(id);
+                topology.cache_bytes[level as usize - 1] += parse_cache_size(&size);
+            }
+        }
+    }
+    topology.packages = packages.len();
+    let count_cores = |cpu_indexes: &[u32]| {
+        let mut distinct: Vec<&String> = cores.iter()
+            .filter(|(index, _)| cpu_indexes.contains(index))
+            .map(|(_, core)| core)
+            .collect();
+        distinct.sort();
+        distinct.dedup();
+        distinct.len()
+    };
+    let all_cpus: Vec<u32> = cpus.iter().map(|(index, _)| *index).collect();
+    topology.cores = count_cores(&all_cpus);
+
+    let intel_hybrid = read_sysfs(&devices_dir.join("cpu_core"), "cpus")
+        .zip(read_sysfs(&devices_dir.join("cpu_atom"), "cpus"))
+        .map(|(performance, efficiency)| (parse_cpu_list(&performance), parse_cpu_list(&efficiency)));
+    let capacity_hybrid = || {
+        let capacities: Vec<(u32, u64)> = cpus.iter()
+            .filter_map(|(index, cpu)| Some((*index, read_sysfs_u64(cpu, "cpu_capacity")?)))
+            .collect();
+        let highest = capacities.iter().map(|(_, capacity)| *capacity).max()?;
+        let (performance, efficiency): (Vec<_>, Vec<_>) = capacities.iter().partition(|(_, capacity)| *capacity == highest);
+        let indexes = |cpus: Vec<&(u32, u64)>| cpus.into_iter().map(|(index, _)| *index).collect::<Vec<u32>>();
+        Some((indexes(performance), indexes(efficiency)))
+    };
+    topology.hybrid = intel_hybrid.or_else(capacity_hybrid)
+        .filter(|(performance, efficiency)| !performance.is_empty() && !efficiency.is_empty())
+        .map(|(performance, efficiency)| (count_cores(&performance), count_cores(&efficiency)));
+    topology
+}
+
+/// Parses a CPU list like "0-7,16-23" into CPU numbers
+fn parse_cpu_list(list: &str) -> Vec<u32> {
+    list.split(',')
+        .filter_map(|range| match range.trim().split_once('-') {
+            Some((start, end)) => Some((start.parse().ok()?..=end.parse().ok()?).collect::<Vec<u32>>()),
+            None => Some(vec![range.trim().parse().ok()?]),
+        })
+        .flatten()
+        .collect()
+}
+
+/// Parses a cache size like "48K" or "2M" into bytes
+fn parse_cache_size(size: &str) -> u64 {
+    let (number, multiplier) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1024),
+        Some('M') => (&size[..size.len() - 1], 1048576),
+        Some('G') => (&size[..size.len() - 1], 1073741824),
+        _ => (size, 1),
+    };
+    number.parse::<u64>().unwrap_or(0) * multiplier
+}
+
+/// Measures CPU usage from two samples of /proc/stat, sleeping asynchronously in between so other modules keep running
+/// Options: "interval_ms" - time between the samples (default 200), "per_core" - also show usage of every CPU (default false),
+/// "bar" - show a bar graph (default true), "bar_width" - length of the bar graph (default 10)
+async fn fetch_cpu_usage(config: ModuleConfig) -> ModuleResult {
+    let interval = config.f64_option("interval_ms", 200.0).clamp(10.0, 5000.0) as u64;
+    let Some(before) = fs::read_to_string("/proc/stat").ok().map(|stat| parse_cpu_times(&stat)) else {
+        return ModuleResult::failed("cpu_usage", "CPU Usage", "Unable to read /proc/stat");
+    };
+    tokio::time::sleep(Duration::from_millis(interval)).await;
+    let after = fs::read_to_string("/proc/stat").map(|stat| parse_cpu_times(&stat)).unwrap_or_default();
+
+    // Usage is the share of time spent not idle between the samples, CPUs are matched by name in case one went offline
+    let usage: Vec<(String, f64)> = after.iter()
+        .filter_map(|(name, busy, total)| {
+            let (_, busy_before, total_before) = before.iter().find(|(before_name, _, _)| before_name == name)?;
+            let total = total.saturating_sub(*total_before);
+            let percent = if total == 0 { 0.0 } else { busy.saturating_sub(*busy_before) as f64 * 100.0 / total as f64 };
+            Some((name.clone(), percent))
+        })
+        .collect();
+    let Some((_, percent)) = usage.iter().find(|(name, _)| name == "cpu") else {
+        return ModuleResult::failed("cpu_usage", "CPU Usage", "No CPU found");
+    };
+
+    let mut result = ModuleResult::new("cpu_usage", "CPU Usage").with("percent", *percent);
+    if config.bool_option("bar", true) {
+        let width = config.f64_option("bar_width", 10.0).clamp(1.0, 100.0) as usize;
+        let filled = ((percent / 100.0 * width as f64).round() as usize).min(width);
+        result = result.with("bar", format!("{}{}", "█".repeat(filled), "░".repeat(width - filled)));
+    }
+    if config.bool_option("per_core", false) {
+        let cores: Vec<Value> = usage.iter()
+            .filter(|(name, _)| name != "cpu")
+            .map(|(name, percent)| serde_json::json!({ "name": name, "percent": percent }))
+            .collect();
+        result = result.with("cores", cores);
+    }
+    result
+}
+
+/// Parses the cpu lines of /proc/stat into the CPU name, busy and total time in clock ticks
+/// The first line, "cpu", sums up all CPUs. Guest time is already counted in user time, so it's left out
+fn parse_cpu_times(stat: &str) -> Vec<(String, u64, u64)> {
+    stat.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let times: Vec<u64> = fields.take(8).filter_map(|time| time.parse().ok()).collect();
+            // Idle and iowait
+            let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+            let total: u64 = times.iter().sum();
+            Some((name, total - idle, total))
+        })
+        .collect()
 }
 /// Fetches used_swap/total_swap - (used)%
-async fn fetch_swap() -> String {
+async fn fetch_swap() -> ModuleResult {
     let sys = System::new_with_specifics(
         RefreshKind::nothing().with_memory(MemoryRefreshKind::everything())
     );
     let total_swap = sys.total_swap();
     let used_swap = sys.used_swap();
-    let swap_percentage = (used_swap as f64 / total_swap as f64) * 100.0;
-    let swap_string = format!(
-        "$3Swap Used: $2{:.2} $3GiB / $2{:.2} $3GiB $4({}%)$2",
-        used_swap as f64 / 1073741824.0,
-        total_swap as f64 / 1073741824.0,
-        swap_percentage as u8
-    );
-    swap_string
+    ModuleResult::new("swap", "Swap Used")
+        .with("used_bytes", used_swap)
+        .with("total_bytes", total_swap)
+        .with("percent", percent_of(used_swap, total_swap))
 }
 /// Fetches disk information using sysinfo and/or /proc/mounts
-async fn fetch_disks() -> String {
+/// Options: "mounts" - list of mount points to show instead of detecting them, "show_fs" - show file system (default true)
+async fn fetch_disks(config: ModuleConfig) -> ModuleResult {
     let disks = Disks::new_with_refreshed_list();
-    let mut buffer = String::new();
+    let show_fs = config.bool_option("show_fs", true);
+
+    // Mount points picked in config are shown in the given order, skipping detection
+    if let Some(mounts) = config.str_list_option("mounts") {
+        let selected_disks: Vec<&Disk> = mounts.iter()
+            .filter_map(|mount| disks.list().iter().find(|disk| disk.mount_point() == Path::new(mount)))
+            .collect();
+        return disks_result(selected_disks, show_fs);
+    }
+
     let mut selected_disks = vec![];
     let mut root_btrfs_disk = None;
     let mut min_subvolid_disk = None;
@@ -233,7 +601,7 @@ async fn fetch_disks() -> String {
                 mounts = match File::open("/proc/mounts") {
                     Ok(file) => Some(BufReader::new(file)
                         .lines()
-                        .filter_map(Result::ok)
+                        .map_while(Result::ok)
                         .collect::<Vec<String>>()),
                     Err(_) => None,
                 };
@@ -247,8 +615,8 @@ async fn fetch_disks() -> String {
                             root_btrfs_disk = Some(disk);
                             break;
                         }
-                        if let Some(subvolid) = parts.iter().find(|&&part| part.starts_with("subvolid=")) {
-                            let subvolid_value = subvolid[9..].parse().unwrap_or(u32::MAX);
+                        if let Some(subvolid) = parts.iter().find_map(|part| part.strip_prefix("subvolid=")) {
+                            let subvolid_value = subvolid.parse().unwrap_or(u32::MAX);
                             if subvolid_value < min_subvolid {
                                 min_subvolid = subvolid_value;
                                 min_subvolid_disk = Some(disk);
@@ -264,116 +632,1243 @@ async fn fetch_disks() -> String {
 
     // Prefer root btrfs disk, fall back to the one with the smallest subvolid
     if let Some(disk) = root_btrfs_disk.or(min_subvolid_disk) {
-        buffer.push_str(&format_disk_info(disk));
-    } else if !selected_disks.is_empty() {
-        for disk in selected_disks {
-            buffer.push_str(&format_disk_info(disk));
-        }
-    } else {
-        buffer.push_str("$3Disk: $2Unknown\n");
+        selected_disks = vec![disk];
     }
 
-    buffer
+    disks_result(selected_disks, show_fs)
 }
 
-/// Formats disk information
-fn format_disk_info(disk: &Disk) -> String {
-    let file_system = disk.file_system().to_string_lossy();
-    let mount_point = disk.mount_point().to_string_lossy();
+/// Builds the disks result with one sub-entry per selected disk
+fn disks_result(selected_disks: Vec<&Disk>, show_fs: bool) -> ModuleResult {
+    if selected_disks.is_empty() {
+        return ModuleResult::failed("disks", "Disk", "Unknown");
+    }
+    selected_disks.into_iter()
+        .fold(ModuleResult::new("disks", "Disk"), |result, disk| result.with_entry(disk_info(disk, show_fs)))
+}
+
+/// Collects information about a single disk
+fn disk_info(disk: &Disk, show_fs: bool) -> ModuleResult {
     let size = disk.total_space();
     let used = size - disk.available_space();
-    let used_percentage = (used as f64 / size as f64) * 100.0;
-    format!("$3Disk ({}): $2{:.2} $3GiB / $2{:.2} $3GiB $4({}%)$2 - {}\n",
-        mount_point,
-        used as f64 / 1073741824.0,
-        size as f64 / 1073741824.0,
-        used_percentage as u8,
-        file_system
-    )
-}
-/// Checks what terminal you're using by trying environment variables associated with common terminal emulators
-/// If no matches are found, prints the terminals framework
-async fn fetch_terminal_emulator() -> String {
-    // Check common emulators
-    let mut buffer = "$3Terminal: $2".to_string();
-    if let Ok(_) = env::var("ALACRITTY_LOG") {
-        buffer.push_str("alacritty");
-        return buffer;
-    }
-    if let Ok(_) = env::var("TERMINATOR_UUID") {
-        buffer.push_str("terminator");
-        return buffer;
-    }
-    if let Ok(_) = env::var("VTE_VERSION") {
-        buffer.push_str("gnome-terminal");
-        return buffer;
-    }
-    if let Ok(_) = env::var("KONSOLE_PROFILE_NAME") {
-        buffer.push_str("kosnole");
-        return buffer;
-    }
-    if let Ok(term_program) = env::var("TERM_PROGRAM") {
-        match term_program.as_str() {
-            "Apple_Terminal" => buffer.push_str("terminal"),
-            "iTerm.app" => buffer.push_str("iTerm"),
-            "Hyper" => buffer.push_str("hyper"),
-            "Kitty" => buffer.push_str("kitty"),
-            "vscode" => buffer.push_str("vscode"),
-            _ => buffer.push_str(&term_program),
-        }
-    }
-    // If nothing found check terminal framework
-    if let Ok(term) = env::var("TERM") {
-        match term.as_str() {
-            "xterm-kitty" => buffer.push_str("kitty"),
-            "tmux-256color" => buffer.push_str("tmux"),
-            "screen-256color" => buffer.push_str("screen"),
-            "linux" => buffer.push_str("tty"),
-            _ => (),
-        }
-    }
-    buffer
-}
-/// Fetches color palette file from embeded "assets" directory
-async fn fetch_color_palette() -> String {
-            let path = format!("ansi/palette.ansi.txt");
-            if let Some(file) = ASSETS.get_file(path) {
-                let contents = file.contents_utf8().unwrap();
-                contents.to_string()
-            } else {
-                "Palette file not found".to_string()
+    let mut info = ModuleResult::new("disks", "Disk")
+        .with("mount", disk.mount_point().to_string_lossy());
+    if show_fs {
+        info = info.with("fs", disk.file_system().to_string_lossy());
+    }
+    info.with("used_bytes", used)
+        .with("total_bytes", size)
+        .with("percent", percent_of(used, size))
+}
+/// Checks what terminal you're using by walking up the parent processes until reaching the terminal emulator
+/// Shells, ssh and other wrappers are skipped, and multiplexers like tmux are reported separately
+/// If the walk doesn't find a known terminal, tries environment variables associated with common terminal emulators,
+/// and only then falls back to the name of the first other process on the way
+async fn fetch_terminal_emulator() -> ModuleResult {
+    let (terminal, unknown_process, multiplexer) = terminal_from_process_tree(Path::new("/proc"), std::process::id());
+    let multiplexer = multiplexer.or_else(|| {
+        if env::var("TMUX").is_ok() {
+            Some("tmux")
+        } else if env::var("STY").is_ok() {
+            Some("screen")
+        } else if env::var("ZELLIJ").is_ok() {
+            Some("zellij")
+        } else {
+            None
+        }
+    });
+    let Some(terminal) = terminal.or_else(terminal_from_env).or(unknown_process) else {
+        return match multiplexer {
+            Some(multiplexer) => ModuleResult::failed("terminal", "Terminal", "Unknown")
+                .with("multiplexer", multiplexer),
+            None => ModuleResult::failed("terminal", "Terminal", "Unknown"),
+        };
+    };
+    let result = ModuleResult::new("terminal", "Terminal").with("name", terminal);
+    match multiplexer {
+        Some(multiplexer) => result.with("multiplexer", multiplexer),
+        None => result,
+    }
+}
+
+/// Walks the parent process chain through /proc/<pid>/stat and /proc/<pid>/comm
+/// Starts from the parent of `pid`, and returns the known terminal emulator, the first unknown process above the nearest shell,
+/// and the multiplexer found on the way
+/// Unknown processes, eg. make or watch, don't stop the walk, as the terminal may be further up
+fn terminal_from_process_tree(proc_dir: &Path, mut pid: u32) -> (Option<String>, Option<String>, Option<&'static str>) {
+    let mut multiplexer = None;
+    let mut unknown_process = None;
+    // Limit the walk in case of a loop, eg. when pids are reused
+    for _ in 0..64 {
+        let Some(parent) = parent_pid(proc_dir, pid).filter(|parent| *parent > 1) else {
+            break;
+        };
+        pid = parent;
+        let Some(name) = process_name(proc_dir, pid) else {
+            break;
+        };
+        // Processes below the shell are wrappers rastfetch was started with, eg. timeout, so the fallback comes from above it
+        if os_map::SHELLS.contains(name.as_str()) {
+            unknown_process = None;
+            continue;
+        }
+        if os_map::SKIPPED_PROCESSES.contains(name.as_str()) {
+            continue;
+        }
+        // Multiplexer servers are detached from the terminal, so the walk continues from one of their clients
+        if let Some((multiplexer_name, client_name)) = multiplexer_server(&name) {
+            multiplexer = Some(multiplexer_name);
+            match processes(proc_dir).into_iter().find(|(client_pid, client)| *client == client_name && *client_pid != pid) {
+                Some((client_pid, _)) => {
+                    pid = client_pid;
+                    continue;
+                }
+                None => break,
             }
+        }
+        if name == "systemd" || name == "init" {
+            break;
+        }
+        if let Some(terminal) = os_map::TERMINALS.get(name.as_str()) {
+            return (Some(terminal.to_string()), unknown_process, multiplexer);
+        }
+        unknown_process.get_or_insert(name);
+    }
+    (None, unknown_process, multiplexer)
+}
+
+/// Returns the multiplexer's name and the process name of its clients, if the process is a multiplexer server
+fn multiplexer_server(name: &str) -> Option<(&'static str, &'static str)> {
+    match name {
+        "tmux: server" => Some(("tmux", "tmux: client")),
+        "SCREEN" => Some(("screen", "screen")),
+        "zellij" => Some(("zellij", "zellij")),
+        _ => None,
+    }
+}
+
+/// Guesses the terminal from environment variables set by common terminal emulators
+fn terminal_from_env() -> Option<String> {
+    let terminal = if env::var("ALACRITTY_LOG").is_ok() || env::var("ALACRITTY_SOCKET").is_ok() {
+        "Alacritty"
+    } else if env::var("KITTY_PID").is_ok() || env::var("TERM").as_deref() == Ok("xterm-kitty") {
+        "kitty"
+    } else if env::var("TERMINATOR_UUID").is_ok() {
+        "Terminator"
+    } else if env::var("KONSOLE_VERSION").is_ok() || env::var("KONSOLE_PROFILE_NAME").is_ok() {
+        "Konsole"
+    } else if env::var("WEZTERM_EXECUTABLE").is_ok() {
+        "WezTerm"
+    } else if let Ok(term_program) = env::var("TERM_PROGRAM").as_deref() && term_program != "tmux" {
+        match term_program {
+            "Apple_Terminal" => "Terminal",
+            "iTerm.app" => "iTerm",
+            "Hyper" => "Hyper",
+            "vscode" => "vscode",
+            "WezTerm" => "WezTerm",
+            "ghostty" => "Ghostty",
+            _ => return Some(term_program.to_string()),
+        }
+    // Many terminals are built on VTE, which doesn't tell which one it is
+    } else if env::var("VTE_VERSION").is_ok() {
+        "VTE-based terminal"
+    } else if env::var("TERM").as_deref() == Ok("linux") {
+        "TTY"
+    } else {
+        return None;
+    };
+    Some(terminal.to_string())
+}
+
+/// Color palette is read from embeded "assets" directory by the renderer
+async fn fetch_color_palette() -> ModuleResult {
+    ModuleResult::new("colors", "Colors")
 }
 /// Fetches bios information from /sys/class/dmi/id/ and /sys/firmware/efi/ directories
-async fn fetch_bios() -> String {
-    let bios_version = fs::read_to_string("/sys/class/dmi/id/bios_version")
-        .unwrap_or_else(|_| "Unknown".to_string());
-    let bios_release =  fs::read_to_string("/sys/class/dmi/id/bios_release")
-        .unwrap_or_else(|_| "Unknown".to_string());
-    let bios_version = bios_version.trim();
-    let bios_release = bios_release.trim();
-    let path = Path::new("/sys/firmware/efi");
-    let bios_type = match path.exists() {
+async fn fetch_bios() -> ModuleResult {
+    let Some(bios_version) = read_dmi("bios_version") else {
+        return ModuleResult::failed("bios", "BIOS", "Unknown");
+    };
+    let bios_release = read_dmi("bios_release").unwrap_or("Unknown".to_string());
+    let bios_type = match Path::new("/sys/firmware/efi").exists() {
         true => "UEFI",
         false => "Legacy",
-    }; 
-    format!("$3BIOS ({}): $2{} {}$1",bios_type ,bios_version, bios_release)
-}  
+    };
+    ModuleResult::new("bios", "BIOS")
+        .with("type", bios_type)
+        .with("version", bios_version)
+        .with("release", bios_release)
+}
 /// Fetches default editor from env variable
-async fn fetch_editor() -> String {
+async fn fetch_editor() -> ModuleResult {
     let editor = env::var("EDITOR").unwrap_or_else(|_| "Unknown".to_string());
-    let editor_name = editor.split('/').last().unwrap_or("Unknown");
-    format!("$3Editor:$2 {}", editor_name)
+    let editor_name = editor.split('/').next_back().unwrap_or("Unknown");
+    ModuleResult::new("editor", "Editor").with("name", editor_name)
+}
+
+async fn fetch_platform() -> ModuleResult {
+    ModuleResult::new("platform", "Platform").with("name", whoami::platform().to_string())
+}
+
+async fn fetch_chassis() -> ModuleResult {
+    let Some(chassis_code) = read_chassis_code() else {
+        return ModuleResult::failed("chassis", "Chassis", "Unknown");
+    };
+    let Some(chassis_type) = os_map::CHASSIS_TYPES.get(chassis_code.as_str()) else {
+        return ModuleResult::failed("chassis", "Chassis", "Unknown chassis code");
+    };
+    // Hypervisors usually report "Other" or "Unknown", which says nothing about the machine
+    let chassis_type = match (chassis_code.as_str(), detect_hypervisor()) {
+        ("1" | "2", Some(_)) => "Virtual Machine",
+        _ => chassis_type,
+    };
+    ModuleResult::new("chassis", "Chassis")
+        .with("code", chassis_code)
+        .with("type", chassis_type)
+}
+
+/// Reads the SMBIOS chassis type code from /sys/class/dmi/id/chassis_type
+fn read_chassis_code() -> Option<String> {
+    read_dmi("chassis_type")
+}
+
+/// Fetches the machine's vendor, product name and version from /sys/class/dmi/id/
+/// Falls back to the device tree model on machines without DMI, eg. ARM boards like Raspberry Pi
+async fn fetch_host() -> ModuleResult {
+    let result = dmi_result("host", "Host", ["sys_vendor", "product_name", "product_version"]);
+    if !result.is_empty() {
+        return result;
+    }
+    // Device tree strings are NUL terminated
+    match fs::read_to_string("/sys/firmware/devicetree/base/model") {
+        Ok(model) => result.with("name", model.trim_end_matches('\0').trim()),
+        Err(_) => ModuleResult::failed("host", "Host", "Unknown"),
+    }
+}
+
+/// Detects the hypervisor and container rastfetch runs in, and whether it runs in a chroot
+/// Hides itself on bare metal outside of containers
+async fn fetch_virtualization() -> ModuleResult {
+    let mut result = ModuleResult::new("virtualization", "Virtualization");
+    if let Some(hypervisor) = detect_hypervisor() {
+        result = result.with("hypervisor", hypervisor);
+    }
+    if let Some(container) = detect_container() {
+        result = result.with("container", container);
+    }
+    if detect_chroot() {
+        result = result.with("chroot", true);
+    }
+    result
 }
 
-async fn fetch_platform() -> String {
-    let platform = whoami::platform();
-    format!("$3Platform: $2{}", platform)
+/// Substrings of DMI vendor and product names identifying hypervisors, checked in order
+const HYPERVISOR_VENDORS: [(&str, &str); 11] = [
+    ("QEMU", "QEMU"),
+    ("KVM", "KVM"),
+    ("Amazon EC2", "KVM (Amazon EC2)"),
+    ("Google Compute Engine", "KVM (Google Compute Engine)"),
+    ("VMware", "VMware"),
+    ("innotek", "VirtualBox"),
+    ("VirtualBox", "VirtualBox"),
+    ("Virtual Machine", "Hyper-V"),
+    ("Xen", "Xen"),
+    ("Parallels", "Parallels"),
+    ("Bochs", "Bochs"),
+];
+
+/// Detects the hypervisor from WSL's kernel release, DMI vendor and product names, /sys/hypervisor and the hypervisor CPU flag
+fn detect_hypervisor() -> Option<String> {
+    // WSL 2 runs in a Hyper-V VM, WSL 1 translates Linux syscalls without any VM
+    if let Ok(release) = fs::read_to_string("/proc/sys/kernel/osrelease") {
+        let release = release.to_lowercase();
+        if release.contains("wsl2") || release.contains("microsoft-standard") {
+            return Some("WSL 2".to_string());
+        }
+        if release.contains("microsoft") {
+            return Some("WSL 1".to_string());
+        }
+    }
+    let dmi: Vec<String> = ["sys_vendor", "product_name", "board_vendor", "bios_vendor"].iter()
+        .filter_map(|attribute| read_dmi(attribute))
+        .collect();
+    let from_dmi = HYPERVISOR_VENDORS.iter()
+        .find(|(needle, _)| dmi.iter().any(|value| value.contains(needle)))
+        .map(|(_, hypervisor)| hypervisor.to_string());
+    if from_dmi.is_some() {
+        return from_dmi;
+    }
+    // Xen guests without DMI, like paravirtualized ones, expose the hypervisor type here
+    if let Some(hypervisor) = read_sysfs(Path::new("/sys/hypervisor"), "type").filter(|hypervisor| !hypervisor.is_empty()) {
+        return Some(if hypervisor == "xen" { "Xen".to_string() } else { hypervisor });
+    }
+    // CPUs report this flag under every hypervisor, even ones that hide their name
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .find(|line| line.starts_with("flags"))?
+        .split_whitespace()
+        .any(|flag| flag == "hypervisor")
+        .then(|| "Unknown hypervisor".to_string())
 }
 
-async fn fetch_chassis() -> String {
-    let chassis_code = fs::read_to_string("/sys/class/dmi/id/chassis_type").unwrap_or("Unknown".to_string());
-    let code_trimmed = chassis_code.trim();
-    let chassis_type = *os_map::CHASSIS_TYPES.get(code_trimmed).unwrap_or(&"Unknown chassis code");
-    format!("$3Chassis: $2{}", chassis_type)
-}
\ No newline at end of file
+/// Detects container engines from the files they create and from the cgroup of pid 1
+fn detect_container() -> Option<String> {
+    if Path::new("/.dockerenv").exists() {
+        return Some("Docker".to_string());
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("Podman".to_string());
+    }
+    // Set by systemd-nspawn, LXC and others following the systemd container interface
+    if let Some(container) = read_sysfs(Path::new("/run/systemd"), "container").filter(|container| !container.is_empty()) {
+        return Some(match container.as_str() {
+            "systemd-nspawn" => "systemd-nspawn".to_string(),
+            "lxc" | "lxc-libvirt" => "LXC".to_string(),
+            "docker" => "Docker".to_string(),
+            "podman" => "Podman".to_string(),
+            _ => container,
+        });
+    }
+    let cgroup = fs::read_to_string("/proc/1/cgroup").ok()?;
+    [("/docker", "Docker"), ("libpod", "Podman"), ("/lxc", "LXC"), ("kubepods", "Kubernetes"), ("machine.slice", "systemd-nspawn")]
+        .iter()
+        .find(|(needle, _)| cgroup.contains(needle))
+        .map(|(_, container)| container.to_string())
+}
+
+/// Checks if the root directory differs from the one of pid 1, which can only be read as root or by pid 1's owner
+fn detect_chroot() -> bool {
+    match (fs::metadata("/proc/1/root"), fs::metadata("/")) {
+        (Ok(init_root), Ok(root)) => (init_root.dev(), init_root.ino()) != (root.dev(), root.ino()),
+        _ => false,
+    }
+}
+
+/// Fetches the motherboard's vendor, name and version from /sys/class/dmi/id/
+async fn fetch_board() -> ModuleResult {
+    let result = dmi_result("board", "Board", ["board_vendor", "board_name", "board_version"]);
+    if result.is_empty() {
+        return ModuleResult::failed("board", "Board", "Unknown");
+    }
+    result
+}
+
+/// Builds a result with "vendor", "name" and "version" fields from the given DMI attributes, leaving out missing ones
+fn dmi_result(module: &'static str, key: &str, attributes: [&str; 3]) -> ModuleResult {
+    ["vendor", "name", "version"].into_iter()
+        .zip(attributes)
+        .fold(ModuleResult::new(module, key), |result, (field, attribute)| match read_dmi(attribute) {
+            Some(value) => result.with(field, value),
+            None => result,
+        })
+}
+
+/// Reads an attribute from /sys/class/dmi/id/, skipping empty values and placeholders left by vendors like "To Be Filled By O.E.M."
+fn read_dmi(attribute: &str) -> Option<String> {
+    read_sysfs(Path::new("/sys/class/dmi/id"), attribute)
+        .filter(|value| !value.is_empty() && !os_map::DMI_PLACEHOLDERS.contains(value.to_lowercase().as_str()))
+}
+/// Fetches all GPUs from /sys/class/drm/card*/device, with names resolved through the pci.ids database
+/// Reports "none" if there is no GPU, eg. on headless machines
+/// Options: "temperature" - set to false to hide the temperature, "high" and "critical" - same as for sensors
+async fn fetch_gpu(config: ModuleConfig) -> ModuleResult {
+    let gpus = read_gpus(Path::new("/sys/class/drm"));
+    if gpus.is_empty() {
+        return ModuleResult::new("gpu", "GPU").with("name", "none");
+    }
+    let pci_ids = PCI_IDS_PATHS.iter().find_map(|path| fs::read_to_string(path).ok());
+    let thresholds = config.bool_option("temperature", true).then(|| Thresholds::from_config(&config));
+    gpus.into_iter().fold(ModuleResult::new("gpu", "GPU"), |result, gpu| {
+        result.with_entry(gpu_info(gpu, pci_ids.as_deref(), thresholds.as_ref()))
+    })
+}
+
+/// Places where distros install the PCI ID database
+const PCI_IDS_PATHS: [&str; 4] = [
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+    "/usr/local/share/pci.ids",
+];
+
+/// Raw information about a GPU read from sysfs
+struct Gpu {
+    vendor_id: Option<u16>,
+    device_id: Option<u16>,
+    driver: Option<String>,
+    vram_bytes: Option<u64>,
+    temperature: Option<f64>,
+}
+
+/// Reads all cardN devices in the drm directory, skipping connectors like card0-HDMI-A-1
+fn read_gpus(drm_dir: &Path) -> Vec<Gpu> {
+    numbered_entries(drm_dir, "card", "")
+        .into_iter()
+        .map(|card| card.join("device"))
+        .filter(|device| device.exists())
+        .map(|device| Gpu {
+            vendor_id: read_hex_id(&device.join("vendor")),
+            device_id: read_hex_id(&device.join("device")),
+            driver: fs::read_link(device.join("driver"))
+                .ok()
+                .and_then(|driver| driver.file_name().map(|name| name.to_string_lossy().into_owned())),
+            vram_bytes: fs::read_to_string(device.join("mem_info_vram_total"))
+                .ok()
+                .and_then(|vram| vram.trim().parse().ok()),
+            // GPU drivers like amdgpu, nouveau and xe register their own hwmon chip, its first sensor is the GPU die
+            temperature: read_hwmon(&device.join("hwmon"))
+                .into_iter()
+                .find_map(|chip| chip.temperatures.first().map(|(_, celsius)| *celsius)),
+        })
+        .collect()
+}
+
+/// Reads a sysfs file containing a hexadecimal ID like "0x10de"
+fn read_hex_id(path: &Path) -> Option<u16> {
+    let content = fs::read_to_string(path).ok()?;
+    u16::from_str_radix(content.trim().trim_start_matches("0x"), 16).ok()
+}
+
+/// Builds the result for a single GPU, falling back to the embedded vendor list if there is no pci.ids database
+fn gpu_info(gpu: Gpu, pci_ids: Option<&str>, thresholds: Option<&Thresholds>) -> ModuleResult {
+    let (vendor, device) = match (gpu.vendor_id, gpu.device_id) {
+        (Some(vendor_id), Some(device_id)) => {
+            let (vendor, device) = pci_ids
+                .map(|pci_ids| lookup_pci_ids(pci_ids, vendor_id, device_id))
+                .unwrap_or((None, None));
+            let vendor = vendor.or_else(|| {
+                os_map::PCI_VENDORS.get(&vendor_id).map(|vendor| vendor.to_string())
+            });
+            (vendor, device)
+        }
+        _ => (None, None),
+    };
+
+    let name = match (&vendor, &device) {
+        (Some(vendor), Some(device)) => format!("{} {}", vendor, device),
+        (Some(vendor), None) => format!("{} [{:04x}]", vendor, gpu.device_id.unwrap_or(0)),
+        _ => gpu.driver.clone().unwrap_or("Unknown".to_string()),
+    };
+
+    let mut result = ModuleResult::new("gpu", "GPU").with("name", name);
+    if let Some(vendor) = vendor {
+        result = result.with("vendor", vendor);
+    }
+    if let Some(vendor_id) = gpu.vendor_id {
+        result = result.with("vendor_id", format!("{:04x}", vendor_id));
+    }
+    if let Some(device_id) = gpu.device_id {
+        result = result.with("device_id", format!("{:04x}", device_id));
+    }
+    if let Some(driver) = gpu.driver {
+        result = result.with("driver", driver);
+    }
+    if let Some(vram_bytes) = gpu.vram_bytes {
+        result = result.with("vram_bytes", vram_bytes);
+    }
+    if let (Some(celsius), Some(thresholds)) = (gpu.temperature, thresholds) {
+        result = with_temperature(result, celsius, thresholds);
+    }
+    result
+}
+
+/// Finds vendor and device names in the pci.ids database
+/// Vendors are lines starting with a hex ID, their devices follow on lines indented with one tab
+fn lookup_pci_ids(pci_ids: &str, vendor_id: u16, device_id: u16) -> (Option<String>, Option<String>) {
+    let vendor_prefix = format!("{:04x}  ", vendor_id);
+    let device_prefix = format!("\t{:04x}  ", device_id);
+    let mut lines = pci_ids.lines();
+    let Some(vendor) = lines.find_map(|line| line.strip_prefix(&vendor_prefix)) else {
+        return (None, None);
+    };
+    let device = lines
+        .take_while(|line| line.starts_with('\t') || line.starts_with('#') || line.is_empty())
+        .find_map(|line| line.strip_prefix(&device_prefix));
+    (Some(vendor.to_string()), device.map(str::to_string))
+}
+
+/// Counts installed packages of every package manager found, reading their databases directly instead of running them
+async fn fetch_packages() -> ModuleResult {
+    let home = env::var("HOME").map(PathBuf::from).ok();
+    let in_home = |count: fn(&Path) -> usize, dir: &str| home.as_ref().map_or(0, |home| count(&home.join(dir)));
+    let mut counts: Vec<(&str, usize)> = vec![
+        ("dpkg", count_dpkg_packages(Path::new("/var/lib/dpkg/status"))),
+        ("rpm", count_rpm_packages(Path::new("/var/lib/rpm"))),
+        ("pacman", count_dirs(Path::new("/var/lib/pacman/local"))),
+        ("apk", count_lines_starting_with(Path::new("/lib/apk/db/installed"), "P:")),
+        ("xbps", count_xbps_packages(Path::new("/var/db/xbps"))),
+        ("nix-system", count_nix_packages(Path::new("/run/current-system/sw"))),
+        ("nix-default", count_nix_packages(Path::new("/nix/var/nix/profiles/default"))),
+        ("nix-user", in_home(count_nix_packages, ".nix-profile")),
+        ("flatpak-system", count_flatpak_packages(Path::new("/var/lib/flatpak"))),
+        ("flatpak-user", in_home(count_flatpak_packages, ".local/share/flatpak")),
+        ("snap", count_snap_packages(Path::new("/snap"))),
+    ];
+    counts.retain(|(_, count)| *count > 0);
+    if counts.is_empty() {
+        return ModuleResult::failed("packages", "Packages", "No package manager found");
+    }
+
+    // Nix profiles and flatpak installations add up to one count per package manager, eg. "flatpak" from flatpak-system and flatpak-user
+    let mut managers: Vec<(&str, usize)> = Vec::new();
+    for (scope, count) in &counts {
+        let manager = scope.split('-').next().unwrap_or(scope);
+        match managers.iter_mut().find(|(name, _)| *name == manager) {
+            Some((_, sum)) => *sum += count,
+            None => managers.push((manager, *count)),
+        }
+    }
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    let result = managers.into_iter()
+        .fold(ModuleResult::new("packages", "Packages"), |result, (manager, count)| result.with(manager, count));
+    counts.into_iter()
+        .filter(|(scope, _)| scope.contains('-'))
+        .fold(result, |result, (scope, count)| result.with(scope, count))
+        .with("total", total)
+}
+
+/// Counts packages marked as installed in dpkg's status file
+fn count_dpkg_packages(status: &Path) -> usize {
+    fs::read_to_string(status)
+        .map(|status| status.lines().filter(|line| *line == "Status: install ok installed").count())
+        .unwrap_or(0)
+}
+
+/// Counts rows of the Packages table in rpmdb.sqlite, used by rpm since 4.16
+/// Older BerkeleyDB "Packages" files, eg. on RHEL 8 and CentOS 7, and ndb "Packages.db" on openSUSE aren't read, so they count as 0
+fn count_rpm_packages(rpm_dir: &Path) -> usize {
+    sqlite::count_rows(&rpm_dir.join("rpmdb.sqlite"), "Packages").unwrap_or(0)
+}
+
+/// Counts lines with a given prefix, eg. "P:" package names in apk's database
+fn count_lines_starting_with(path: &Path, prefix: &str) -> usize {
+    fs::read_to_string(path)
+        .map(|content| content.lines().filter(|line| line.starts_with(prefix)).count())
+        .unwrap_or(0)
+}
+
+/// Counts subdirectories, eg. one per package in pacman's local database
+fn count_dirs(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.map_while(Result::ok).filter(|entry| entry.path().is_dir()).count())
+        .unwrap_or(0)
+}
+
+/// Counts packages in xbps' pkgdb-*.plist, where every package has a "pkgver" key
+fn count_xbps_packages(xbps_dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(xbps_dir) else {
+        return 0;
+    };
+    entries.map_while(Result::ok)
+        .find(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with("pkgdb-") && name.ends_with(".plist")
+        })
+        .and_then(|entry| fs::read_to_string(entry.path()).ok())
+        .map(|pkgdb| pkgdb.matches("<key>pkgver</key>").count())
+        .unwrap_or(0)
+}
+
+/// Counts packages in a Nix profile
+/// Profiles managed by `nix profile` list them in manifest.json, otherwise distinct store paths linked from bin/ are counted
+fn count_nix_packages(profile: &Path) -> usize {
+    if let Ok(manifest) = fs::read_to_string(profile.join("manifest.json"))
+        && let Ok(manifest) = serde_json::from_str::<Value>(&manifest) {
+        return match &manifest["elements"] {
+            Value::Array(elements) => elements.len(),
+            Value::Object(elements) => elements.len(),
+            _ => 0,
+        };
+    }
+    let Ok(entries) = fs::read_dir(profile.join("bin")) else {
+        return 0;
+    };
+    let mut store_paths: Vec<PathBuf> = entries.map_while(Result::ok)
+        .filter_map(|entry| fs::read_link(entry.path()).ok())
+        .map(|target| target.components().take(4).collect::<PathBuf>())
+        .filter(|store_path| store_path.starts_with("/nix/store"))
+        .collect();
+    store_paths.sort();
+    store_paths.dedup();
+    store_paths.len()
+}
+
+/// Counts installed Flatpak apps and runtimes, one per name/arch/branch directory
+fn count_flatpak_packages(flatpak_dir: &Path) -> usize {
+    ["app", "runtime"].iter()
+        .filter_map(|kind| fs::read_dir(flatpak_dir.join(kind)).ok())
+        .flat_map(|names| names.map_while(Result::ok))
+        .filter_map(|name| fs::read_dir(name.path()).ok())
+        .flat_map(|arches| arches.map_while(Result::ok))
+        .filter(|arch| arch.file_name() != "current")
+        .map(|arch| count_dirs(&arch.path()))
+        .sum()
+}
+
+/// Counts snaps mounted in /snap, which also contains a "bin" directory with wrappers
+fn count_snap_packages(snap_dir: &Path) -> usize {
+    fs::read_dir(snap_dir)
+        .map(|entries| {
+            entries.map_while(Result::ok)
+                .filter(|entry| entry.file_name() != "bin" && entry.path().is_dir())
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Checks what desktop environment you're using from XDG_CURRENT_DESKTOP and DESKTOP_SESSION env variables
+/// If they are not set, looks for known session processes
+/// Hides itself when nothing is found outside of a graphical session, eg. on servers and over SSH
+async fn fetch_desktop_environment() -> ModuleResult {
+    let from_env = env::var("XDG_CURRENT_DESKTOP")
+        .ok()
+        // Values like "ubuntu:GNOME" list the most specific desktop first, the actual DE is last
+        .and_then(|desktop| desktop.split(':').rfind(|part| !part.is_empty()).map(str::to_string))
+        .or_else(|| env::var("DESKTOP_SESSION").ok().filter(|session| !session.is_empty()));
+    let desktop = match from_env {
+        Some(desktop) => {
+            let pretty = os_map::DESKTOP_ENVIRONMENTS.get(desktop.to_lowercase().as_str()).copied();
+            pretty.map(str::to_string).unwrap_or(desktop)
+        }
+        None => match process_names().iter().find_map(|name| os_map::DE_PROCESSES.get(name.as_str())) {
+            Some(desktop) => desktop.to_string(),
+            None if !graphical_session() => return ModuleResult::new("de", "DE"),
+            None => return ModuleResult::failed("de", "DE", "Unknown"),
+        },
+    };
+    with_session_type(ModuleResult::new("de", "DE").with("name", desktop))
+}
+
+/// Checks what window manager or compositor is running by scanning /proc/*/comm for known names
+/// Hides itself when nothing is found outside of a graphical session, like the DE module
+async fn fetch_window_manager() -> ModuleResult {
+    match process_names().iter().find_map(|name| os_map::WINDOW_MANAGERS.get(name.as_str())) {
+        Some(wm) => with_session_type(ModuleResult::new("wm", "WM").with("name", *wm)),
+        None if !graphical_session() => ModuleResult::new("wm", "WM"),
+        None => ModuleResult::failed("wm", "WM", "Unknown"),
+    }
+}
+
+/// Checks if rastfetch runs inside a Wayland or X11 session
+fn graphical_session() -> bool {
+    ["WAYLAND_DISPLAY", "DISPLAY"].iter().any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+        || matches!(env::var("XDG_SESSION_TYPE").as_deref(), Ok("wayland" | "x11"))
+}
+
+/// Adds the graphical session type, Wayland or X11, to the result if it can be detected
+fn with_session_type(result: ModuleResult) -> ModuleResult {
+    let session_type = match env::var("XDG_SESSION_TYPE").as_deref() {
+        Ok("wayland") => Some("Wayland"),
+        Ok("x11") => Some("X11"),
+        Ok("tty") => Some("TTY"),
+        _ if env::var("WAYLAND_DISPLAY").is_ok() => Some("Wayland"),
+        _ if env::var("DISPLAY").is_ok() => Some("X11"),
+        _ => None,
+    };
+    match session_type {
+        Some(session_type) => result.with("session_type", session_type),
+        None => result,
+    }
+}
+
+/// Returns names of all running processes from /proc/*/comm
+fn process_names() -> Vec<String> {
+    processes(Path::new("/proc")).into_iter().map(|(_, name)| name).collect()
+}
+
+/// Returns pids and names of all running processes from /proc/*/comm, ordered by pid
+fn processes(proc_dir: &Path) -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir(proc_dir) else {
+        return Vec::new();
+    };
+    let mut processes: Vec<(u32, String)> = entries.map_while(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter_map(|pid| Some((pid, process_name(proc_dir, pid)?)))
+        .collect();
+    processes.sort();
+    processes
+}
+
+/// Returns the name of a process from /proc/<pid>/comm
+fn process_name(proc_dir: &Path, pid: u32) -> Option<String> {
+    fs::read_to_string(proc_dir.join(pid.to_string()).join("comm"))
+        .ok()
+        .map(|comm| comm.trim_end().to_string())
+}
+
+/// Returns the parent pid of a process from /proc/<pid>/stat
+fn parent_pid(proc_dir: &Path, pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(proc_dir.join(pid.to_string()).join("stat")).ok()?;
+    // Process name in parentheses can contain spaces, so fields are counted from the last ')'
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Fetches capacity, status, health, cycle count and time remaining of every battery in /sys/class/power_supply
+/// Hides itself on desktops, detected from DMI chassis type, and on machines without a battery
+async fn fetch_battery() -> ModuleResult {
+    if read_chassis_code().is_some_and(|code| os_map::STATIONARY_CHASSIS.contains(code.as_str())) {
+        return ModuleResult::new("battery", "Battery");
+    }
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return ModuleResult::new("battery", "Battery");
+    };
+    let mut supplies: Vec<PathBuf> = entries.map_while(Result::ok).map(|entry| entry.path()).collect();
+    supplies.sort();
+
+    // AC adapters have type "Mains", any of them being online means the machine is plugged in
+    let ac_connected = supplies.iter()
+        .filter(|supply| read_sysfs(supply, "type").as_deref() == Some("Mains"))
+        .any(|supply| read_sysfs(supply, "online").as_deref() == Some("1"));
+
+    supplies.iter()
+        .filter(|supply| supply.file_name().is_some_and(|name| name.to_string_lossy().starts_with("BAT")))
+        .fold(ModuleResult::new("battery", "Battery"), |result, battery| {
+            result.with_entry(battery_info(battery, ac_connected))
+        })
+}
+
+/// Collects information about a single battery
+/// Drivers report either energy (µWh, µW) or charge (µAh, µA) values, both are handled the same way
+fn battery_info(battery: &Path, ac_connected: bool) -> ModuleResult {
+    let name = battery.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let status = read_sysfs(battery, "status").unwrap_or("Unknown".to_string());
+    let mut result = ModuleResult::new("battery", "Battery").with("name", name);
+    if let Some(model) = read_sysfs(battery, "model_name").filter(|model| !model.is_empty()) {
+        result = result.with("model", model);
+    }
+    if let Some(capacity) = read_sysfs_u64(battery, "capacity") {
+        result = result.with("percent", capacity);
+    }
+    result = result.with("status", status.clone());
+
+    let (now, full, full_design, rate) = match read_sysfs_u64(battery, "energy_full") {
+        Some(full) => (
+            read_sysfs_u64(battery, "energy_now"),
+            Some(full),
+            read_sysfs_u64(battery, "energy_full_design"),
+            read_sysfs_u64(battery, "power_now"),
+        ),
+        None => (
+            read_sysfs_u64(battery, "charge_now"),
+            read_sysfs_u64(battery, "charge_full"),
+            read_sysfs_u64(battery, "charge_full_design"),
+            read_sysfs_u64(battery, "current_now"),
+        ),
+    };
+    if let (Some(full), Some(full_design)) = (full, full_design) && full_design > 0 {
+        result = result.with("health_percent", full * 100 / full_design);
+    }
+    if let Some(cycles) = read_sysfs_u64(battery, "cycle_count").filter(|cycles| *cycles > 0) {
+        result = result.with("cycle_count", cycles);
+    }
+    // Time to empty when discharging, time to full when charging, from the current power draw
+    if let (Some(now), Some(full), Some(rate)) = (now, full, rate) && rate > 0 {
+        let remaining = match status.as_str() {
+            "Discharging" => Some(now),
+            "Charging" => Some(full.saturating_sub(now)),
+            _ => None,
+        };
+        if let Some(remaining) = remaining {
+            result = result.with("time_remaining_seconds", remaining * 3600 / rate);
+        }
+    }
+    result.with("ac_connected", ac_connected)
+}
+
+/// Reads a trimmed sysfs attribute
+fn read_sysfs(dir: &Path, attribute: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attribute)).ok().map(|value| value.trim().to_string())
+}
+
+/// Reads a numeric sysfs attribute
+fn read_sysfs_u64(dir: &Path, attribute: &str) -> Option<u64> {
+    read_sysfs(dir, attribute)?.parse().ok()
+}
+
+/// Fetches connected monitors from DRM connectors in /sys/class/drm/card*-*/, with vendor, model and size parsed from EDID
+/// Works without X11 or Wayland, eg. on a TTY
+/// Resolution and refresh rate are the monitor's preferred mode, listed first by the kernel, not necessarily the one in use
+async fn fetch_display() -> ModuleResult {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return ModuleResult::new("display", "Display").with("name", "none");
+    };
+    let mut connectors: Vec<PathBuf> = entries.map_while(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            name.starts_with("card") && name.contains('-')
+        }))
+        .filter(|path| read_sysfs(path, "status").as_deref() == Some("connected"))
+        .collect();
+    connectors.sort();
+    if connectors.is_empty() {
+        return ModuleResult::new("display", "Display").with("name", "none");
+    }
+    connectors.iter().fold(ModuleResult::new("display", "Display"), |result, connector| {
+        result.with_entry(display_info(connector))
+    })
+}
+
+/// Collects information about the monitor plugged into a single connector
+fn display_info(connector: &Path) -> ModuleResult {
+    let name = connector.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    // Connector directories are named like card0-HDMI-A-1
+    let connector_name = name.split_once('-').map_or(name.as_str(), |(_, connector)| connector);
+    let mut result = ModuleResult::new("display", "Display").with("connector", connector_name);
+
+    let mode = fs::read_to_string(connector.join("modes"))
+        .ok()
+        .and_then(|modes| modes.lines().next().map(str::to_string));
+    let resolution = mode.as_deref()
+        .and_then(|mode| mode.split_once('x'))
+        .and_then(|(width, height)| {
+            // Interlaced modes end with "i"
+            Some((width.parse::<u64>().ok()?, height.trim_end_matches('i').parse::<u64>().ok()?))
+        });
+    if let Some((width, height)) = resolution {
+        result = result.with("preferred_width", width).with("preferred_height", height);
+    }
+
+    let Some(edid) = fs::read(connector.join("edid")).ok().and_then(|edid| parse_edid(&edid)) else {
+        return result;
+    };
+    if let Some(refresh) = edid.refresh_hz.filter(|_| resolution.is_none() || resolution == edid.resolution) {
+        result = result.with("preferred_refresh_hz", refresh);
+    }
+    let vendor = os_map::PNP_VENDORS.get(edid.vendor_id.as_str()).map(|vendor| vendor.to_string());
+    result = result.with("vendor_id", edid.vendor_id.clone())
+        .with("vendor", vendor.unwrap_or(edid.vendor_id));
+    if let Some(model) = edid.model {
+        result = result.with("model", model);
+    }
+    if let Some((width_mm, height_mm)) = edid.size_mm {
+        let diagonal = ((width_mm * width_mm + height_mm * height_mm) as f64).sqrt() / 25.4;
+        result = result.with("width_mm", width_mm)
+            .with("height_mm", height_mm)
+            .with("diagonal_inches", (diagonal * 10.0).round() / 10.0);
+    }
+    result
+}
+
+/// Monitor information read from the base EDID block
+struct Edid {
+    /// Three letter PNP manufacturer ID, eg. "DEL"
+    vendor_id: String,
+    /// Monitor name from the display name descriptor
+    model: Option<String>,
+    /// Physical size of the image area in millimeters
+    size_mm: Option<(u64, u64)>,
+    /// Resolution and refresh rate of the preferred detailed timing
+    resolution: Option<(u64, u64)>,
+    refresh_hz: Option<f64>,
+}
+
+/// Parses the 128 byte base EDID block, see VESA E-EDID standard
+fn parse_edid(edid: &[u8]) -> Option<Edid> {
+    if edid.len() < 128 || edid[..8] != [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00] {
+        return None;
+    }
+    // Manufacturer ID is three 5 bit letters, where 1 is 'A'
+    let id = u16::from_be_bytes([edid[8], edid[9]]);
+    let vendor_id: String = [10, 5, 0]
+        .iter()
+        .map(|shift| (b'A' - 1 + ((id >> shift) & 0x1f) as u8) as char)
+        .collect();
+
+    let mut model = None;
+    let mut size_mm = None;
+    let mut resolution = None;
+    let mut refresh_hz = None;
+    for descriptor in edid[54..126].chunks(18) {
+        if descriptor[0] == 0 && descriptor[1] == 0 {
+            // Display descriptor, 0xfc is monitor name, terminated with a newline
+            if descriptor[3] == 0xfc {
+                let name: String = descriptor[5..18].iter()
+                    .take_while(|byte| **byte != 0x0a)
+                    .map(|byte| *byte as char)
+                    .collect();
+                model = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+            }
+        } else if resolution.is_none() {
+            // First detailed timing descriptor is the preferred mode
+            let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as f64 * 10_000.0;
+            let h_active = descriptor[2] as u64 | ((descriptor[4] as u64 >> 4) << 8);
+            let h_blank = descriptor[3] as u64 | ((descriptor[4] as u64 & 0x0f) << 8);
+            let v_active = descriptor[5] as u64 | ((descriptor[7] as u64 >> 4) << 8);
+            let v_blank = descriptor[6] as u64 | ((descriptor[7] as u64 & 0x0f) << 8);
+            let total = ((h_active + h_blank) * (v_active + v_blank)) as f64;
+            resolution = Some((h_active, v_active));
+            if total > 0.0 {
+                refresh_hz = Some((pixel_clock / total * 100.0).round() / 100.0);
+            }
+            let width_mm = descriptor[12] as u64 | ((descriptor[14] as u64 >> 4) << 8);
+            let height_mm = descriptor[13] as u64 | ((descriptor[14] as u64 & 0x0f) << 8);
+            if width_mm > 0 && height_mm > 0 {
+                size_mm = Some((width_mm, height_mm));
+            }
+        }
+    }
+    // Fall back to the rough size in centimeters from the basic display parameters
+    if size_mm.is_none() && edid[21] > 0 && edid[22] > 0 {
+        size_mm = Some((edid[21] as u64 * 10, edid[22] as u64 * 10));
+    }
+    Some(Edid { vendor_id, model, size_mm, resolution, refresh_hz })
+}
+
+/// Fetches non-loopback network interfaces with their IP addresses using sysinfo, and MAC, link speed, state and type from /sys/class/net
+/// Interfaces without an address are skipped, unless picked with "interfaces"
+/// Options: "interfaces" - list of name patterns with * wildcards, eg. ["en*", "wl*"], "show_mac" - show MAC address (default true, false for localip),
+/// "show_ipv6" - show IPv6 addresses (default true)
+async fn fetch_network(config: ModuleConfig, module: &'static str, key: &str) -> ModuleResult {
+    let patterns = config.str_list_option("interfaces");
+    let show_mac = config.bool_option("show_mac", module == "network");
+    let show_ipv6 = config.bool_option("show_ipv6", true);
+
+    let networks = Networks::new_with_refreshed_list();
+    let mut interfaces: Vec<_> = networks.iter()
+        .filter(|(name, _)| read_sysfs(&Path::new("/sys/class/net").join(name), "type").as_deref() != Some("772"))
+        .filter(|(name, _)| name.as_str() != "lo")
+        .filter(|(name, data)| match &patterns {
+            Some(patterns) => patterns.iter().any(|pattern| matches_pattern(pattern, name)),
+            None => !data.ip_networks().is_empty(),
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.0.cmp(b.0));
+    if interfaces.is_empty() {
+        return ModuleResult::failed(module, key, "No network interface found");
+    }
+
+    interfaces.into_iter().fold(ModuleResult::new(module, key), |result, (name, data)| {
+        let sysfs = Path::new("/sys/class/net").join(name);
+        // Link-local IPv6 addresses are present on every interface and not useful at a glance
+        let (ipv4, ipv6): (Vec<_>, Vec<_>) = data.ip_networks().iter()
+            .filter(|network| !matches!(network.addr, std::net::IpAddr::V6(addr) if addr.segments()[0] & 0xffc0 == 0xfe80))
+            .partition(|network| network.addr.is_ipv4());
+        let to_strings = |networks: Vec<&sysinfo::IpNetwork>| -> Vec<String> {
+            networks.iter().map(|network| format!("{}/{}", network.addr, network.prefix)).collect()
+        };
+
+        let mut entry = ModuleResult::new(module, key)
+            .with("interface", name.as_str())
+            .with("ipv4", to_strings(ipv4));
+        if show_ipv6 {
+            entry = entry.with("ipv6", to_strings(ipv6));
+        }
+        if show_mac {
+            entry = entry.with("mac", data.mac_address().to_string());
+        }
+        if let Some(operstate) = read_sysfs(&sysfs, "operstate") {
+            entry = entry.with("operstate", operstate);
+        }
+        // Speed is -1 or unreadable when the link is down or the driver doesn't report it
+        if let Some(speed) = read_sysfs_u64(&sysfs, "speed") {
+            entry = entry.with("speed_mbps", speed);
+        }
+        entry = entry.with("wireless", sysfs.join("wireless").exists() || sysfs.join("phy80211").exists());
+        result.with_entry(entry)
+    })
+}
+
+/// Matches a name against a pattern where * stands for any number of characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|index| name.is_char_boundary(*index))
+                .any(|index| matches_pattern(rest, &name[index..]))
+        }
+    }
+}
+
+/// Fetches temperatures and fan speeds of every hardware monitoring chip in /sys/class/hwmon, one entry per chip
+/// Thermal zones from /sys/class/thermal are added if they aren't already exposed as a hwmon chip
+/// Options: "high" and "critical" - temperatures in °C from which values are shown in a warning color (default 80 and 95)
+async fn fetch_sensors(config: ModuleConfig) -> ModuleResult {
+    sensors_result(Path::new("/sys/class/hwmon"), Path::new("/sys/class/thermal"), &Thresholds::from_config(&config))
+}
+
+/// Builds one entry per hwmon chip and thermal zone, with every temperature classified by the thresholds
+fn sensors_result(hwmon_dir: &Path, thermal_dir: &Path, thresholds: &Thresholds) -> ModuleResult {
+    let mut chips = read_hwmon(hwmon_dir);
+    let hwmon_names: Vec<String> = chips.iter().map(|chip| chip.name.clone()).collect();
+    chips.extend(
+        read_thermal_zones(thermal_dir)
+            .into_iter()
+            .filter(|zone| !hwmon_names.contains(&zone.name))
+    );
+
+    chips.into_iter()
+        .filter(|chip| !chip.temperatures.is_empty() || !chip.fans.is_empty())
+        .fold(ModuleResult::new("sensors", "Sensors"), |result, chip| {
+            let temperatures: Vec<Value> = chip.temperatures.iter()
+                .map(|(label, celsius)| serde_json::json!({
+                    "label": label,
+                    "celsius": celsius,
+                    "level": thresholds.level(*celsius),
+                }))
+                .collect();
+            let fans: Vec<Value> = chip.fans.iter()
+                .map(|(label, rpm)| serde_json::json!({ "label": label, "rpm": rpm }))
+                .collect();
+            let mut entry = ModuleResult::new("sensors", "Sensors")
+                .with("chip", chip.name.as_str())
+                .with("temperatures", temperatures)
+                .with("fans", fans);
+            if let Some(max) = chip.temperatures.iter().map(|(_, celsius)| *celsius).reduce(f64::max) {
+                entry = entry.with("max_celsius", max).with("level", thresholds.level(max));
+            }
+            result.with_entry(entry)
+        })
+}
+
+/// Temperatures and fan speeds reported by a hwmon chip or a thermal zone
+struct SensorChip {
+    name: String,
+    /// Label and temperature in °C
+    temperatures: Vec<(String, f64)>,
+    /// Label and speed in RPM
+    fans: Vec<(String, u64)>,
+}
+
+/// Temperatures from which a value is shown in a warning color, read from the "high" and "critical" options
+struct Thresholds {
+    high: f64,
+    critical: f64,
+}
+
+impl Thresholds {
+    fn from_config(config: &ModuleConfig) -> Self {
+        Thresholds { high: config.f64_option("high", 80.0), critical: config.f64_option("critical", 95.0) }
+    }
+
+    /// Classifies a temperature as "normal", "high" or "critical", the renderer picks its color from that
+    fn level(&self, celsius: f64) -> &'static str {
+        if celsius >= self.critical {
+            "critical"
+        } else if celsius >= self.high {
+            "high"
+        } else {
+            "normal"
+        }
+    }
+}
+
+/// Adds a temperature and its level to a result, used by cpu and gpu
+fn with_temperature(result: ModuleResult, celsius: f64, thresholds: &Thresholds) -> ModuleResult {
+    result.with("temperature_celsius", celsius).with("temperature_level", thresholds.level(celsius))
+}
+
+/// Names of hwmon chips and thermal zones reporting the CPU temperature, in order of preference
+const CPU_SENSORS: [&str; 7] = ["coretemp", "k10temp", "zenpower", "x86_pkg_temp", "cpu_thermal", "cpu-thermal", "soc_thermal"];
+
+/// Returns the CPU temperature, the first sensor of a CPU chip is the package or Tctl temperature
+fn cpu_temperature(hwmon_dir: &Path, thermal_dir: &Path) -> Option<f64> {
+    let mut chips = read_hwmon(hwmon_dir);
+    chips.extend(read_thermal_zones(thermal_dir));
+    CPU_SENSORS.iter().find_map(|name| {
+        chips.iter()
+            .filter(|chip| chip.name == *name)
+            .find_map(|chip| chip.temperatures.first().map(|(_, celsius)| *celsius))
+    })
+}
+
+/// Reads all hwmonN chips in the directory, eg. /sys/class/hwmon or a device's hwmon directory
+fn read_hwmon(hwmon_dir: &Path) -> Vec<SensorChip> {
+    numbered_entries(hwmon_dir, "hwmon", "")
+        .into_iter()
+        .map(|chip| {
+            // Sensors are numbered from 1, temperatures are in millidegrees Celsius
+            let temperatures = numbered_entries(&chip, "temp", "_input").into_iter()
+                .filter_map(|input| {
+                    let (name, label) = sensor_label(&input)?;
+                    let millidegrees: i64 = read_sysfs(&chip, &name)?.parse().ok()?;
+                    Some((label, millidegrees as f64 / 1000.0))
+                })
+                .collect();
+            // Unconnected fan headers report 0 RPM
+            let fans = numbered_entries(&chip, "fan", "_input").into_iter()
+                .filter_map(|input| {
+                    let (name, label) = sensor_label(&input)?;
+                    Some((label, read_sysfs_u64(&chip, &name).filter(|rpm| *rpm > 0)?))
+                })
+                .collect();
+            SensorChip {
+                name: read_sysfs(&chip, "name").unwrap_or("unknown".to_string()),
+                temperatures,
+                fans,
+            }
+        })
+        .collect()
+}
+
+/// Returns the file name of a sensor input like temp1_input, with its label from temp1_label or "temp1" if there is none
+fn sensor_label(input: &Path) -> Option<(String, String)> {
+    let name = input.file_name()?.to_string_lossy().into_owned();
+    let sensor = name.strip_suffix("_input")?;
+    let label = read_sysfs(input.parent()?, &format!("{}_label", sensor))
+        .filter(|label| !label.is_empty())
+        .unwrap_or(sensor.to_string());
+    Some((name, label))
+}
+
+/// Reads all thermal_zoneN directories, every zone becomes a chip named after its type with a single temperature
+/// Zones that have a hwmon link are registered as a hwmon chip too, and are skipped
+fn read_thermal_zones(thermal_dir: &Path) -> Vec<SensorChip> {
+    numbered_entries(thermal_dir, "thermal_zone", "")
+        .into_iter()
+        .filter(|zone| numbered_entries(zone, "hwmon", "").is_empty())
+        .filter_map(|zone| {
+            let millidegrees: i64 = read_sysfs(&zone, "temp")?.parse().ok()?;
+            let label = zone.file_name()?.to_string_lossy().into_owned();
+            Some(SensorChip {
+                name: read_sysfs(&zone, "type").unwrap_or("unknown".to_string()),
+                temperatures: vec![(label, millidegrees as f64 / 1000.0)],
+                fans: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Returns paths of entries named prefix + number + suffix, like card0 or temp1_input, sorted by their number
+fn numbered_entries(dir: &Path, prefix: &str, suffix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut numbered: Vec<(u32, PathBuf)> = entries
+        .map_while(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let index = name.strip_prefix(prefix)?.strip_suffix(suffix)?.parse().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    numbered.sort();
+    numbered.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty directory for a fake sysfs tree, unique per test
+    fn fake_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rastfetch-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a sysfs attribute, creating its directory
+    fn write_attribute(path: PathBuf, value: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{}\n", value)).unwrap();
+    }
+
+    /// Writes /proc/<pid>/stat and /proc/<pid>/comm of a fake process
+    fn write_process(proc_dir: &Path, pid: u32, parent: u32, name: &str) {
+        write_attribute(proc_dir.join(format!("{}/stat", pid)), &format!("{} ({}) S {} {} {} 0", pid, name, parent, pid, pid));
+        write_attribute(proc_dir.join(format!("{}/comm", pid)), name);
+    }
+
+    #[test]
+    fn sensors_from_fake_tree() {
+        let root = fake_dir("sensors");
+        let (hwmon, thermal) = (root.join("hwmon"), root.join("thermal"));
+        write_attribute(hwmon.join("hwmon0/name"), "acpitz");
+        write_attribute(hwmon.join("hwmon0/temp1_input"), "45500");
+        write_attribute(hwmon.join("hwmon0/temp1_label"), "Package");
+        write_attribute(hwmon.join("hwmon0/temp2_input"), "85000");
+        write_attribute(hwmon.join("hwmon0/temp3_input"), "97000");
+        write_attribute(hwmon.join("hwmon0/fan1_input"), "1200");
+        write_attribute(hwmon.join("hwmon0/fan2_input"), "0");
+        // Same name as the hwmon chip
+        write_attribute(thermal.join("thermal_zone0/type"), "acpitz");
+        write_attribute(thermal.join("thermal_zone0/temp"), "30000");
+        // Registered as a hwmon chip through its hwmon link
+        write_attribute(thermal.join("thermal_zone1/type"), "pch_skylake");
+        write_attribute(thermal.join("thermal_zone1/temp"), "40000");
+        fs::create_dir_all(thermal.join("thermal_zone1/hwmon3")).unwrap();
+        write_attribute(thermal.join("thermal_zone2/type"), "x86_pkg_temp");
+        write_attribute(thermal.join("thermal_zone2/temp"), "51250");
+
+        let result = sensors_result(&hwmon, &thermal, &Thresholds { high: 80.0, critical: 95.0 });
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(result.entries.len(), 2);
+        let chip = &result.entries[0];
+        assert_eq!(chip.str_field("chip"), "acpitz");
+        assert_eq!(chip.result["temperatures"], serde_json::json!([
+            { "label": "Package", "celsius": 45.5, "level": "normal" },
+            { "label": "temp2", "celsius": 85.0, "level": "high" },
+            { "label": "temp3", "celsius": 97.0, "level": "critical" },
+        ]));
+        assert_eq!(chip.result["fans"], serde_json::json!([{ "label": "fan1", "rpm": 1200 }]));
+        assert_eq!(chip.f64_field("max_celsius"), 97.0);
+        assert_eq!(chip.str_field("level"), "critical");
+
+        let zone = &result.entries[1];
+        assert_eq!(zone.str_field("chip"), "x86_pkg_temp");
+        assert_eq!(zone.result["temperatures"], serde_json::json!([
+            { "label": "thermal_zone2", "celsius": 51.25, "level": "normal" },
+        ]));
+    }
+
+    #[test]
+    fn terminal_from_fake_tree() {
+        let proc_dir = fake_dir("terminal");
+        // kitty -> zsh -> watch -> rastfetch
+        write_process(&proc_dir, 10, 1, "kitty");
+        write_process(&proc_dir, 11, 10, "zsh");
+        write_process(&proc_dir, 12, 11, "watch");
+        write_process(&proc_dir, 13, 12, "rastfetch");
+        // Unknown emulator -> sshd -> bash -> timeout -> rastfetch
+        write_process(&proc_dir, 20, 1, "myterm");
+        write_process(&proc_dir, 21, 20, "sshd");
+        write_process(&proc_dir, 22, 21, "bash");
+        write_process(&proc_dir, 23, 22, "timeout");
+        write_process(&proc_dir, 24, 23, "rastfetch");
+        // alacritty -> bash -> tmux: client, and tmux: server -> fish -> rastfetch
+        write_process(&proc_dir, 30, 1, "alacritty");
+        write_process(&proc_dir, 31, 30, "bash");
+        write_process(&proc_dir, 32, 31, "tmux: client");
+        write_process(&proc_dir, 40, 1, "tmux: server");
+        write_process(&proc_dir, 41, 40, "fish");
+        write_process(&proc_dir, 42, 41, "rastfetch");
+
+        let kitty = terminal_from_process_tree(&proc_dir, 13);
+        let unknown = terminal_from_process_tree(&proc_dir, 24);
+        let tmux = terminal_from_process_tree(&proc_dir, 42);
+        fs::remove_dir_all(&proc_dir).unwrap();
+
+        assert_eq!(kitty, (Some("kitty".to_string()), None, None));
+        // The wrapper below the shell is not taken for the terminal
+        assert_eq!(unknown, (None, Some("myterm".to_string()), None));
+        assert_eq!(tmux, (Some("Alacritty".to_string()), None, Some("tmux")));
+    }
+
+    #[test]
+    fn shell_from_fake_tree() {
+        let proc_dir = fake_dir("shell");
+        // bash -> fish -> make -> timeout -> rastfetch
+        write_process(&proc_dir, 10, 1, "bash");
+        write_process(&proc_dir, 11, 10, "fish");
+        write_process(&proc_dir, 12, 11, "make");
+        write_process(&proc_dir, 13, 12, "timeout");
+        write_process(&proc_dir, 14, 13, "rastfetch");
+        // systemd -> rastfetch, eg. started from a service without a shell
+        write_process(&proc_dir, 20, 1, "systemd");
+        write_process(&proc_dir, 21, 20, "rastfetch");
+
+        let fish = shell_from_process_tree(&proc_dir, 14);
+        let none = shell_from_process_tree(&proc_dir, 21);
+        fs::remove_dir_all(&proc_dir).unwrap();
+
+        assert_eq!(fish, Some((11, "fish".to_string())));
+        assert_eq!(none, None);
+    }
+}
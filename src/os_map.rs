@@ -1,5 +1,5 @@
 //! This module contains all perfect hash maps used for localising rastfetch for different distros and systems
-use phf::phf_map;
+use phf::{phf_map, phf_set};
 use termcolor::Color;
 
 /// A static perfect hash map, maping the distro's ID found in /etc/os-release (or in case of MacOs its kernel's name) to its logo's name
@@ -68,13 +68,278 @@ pub static CHASSIS_TYPES: phf::Map<&'static str, &'static str> = phf_map! {
     "Unknown" => "Unknown"
 };
 
-/* this part is temporarily disabled
-the function using it is too slow
+/// A static perfect hash set of chassis type codes of machines that don't run on battery, like desktops and servers
+pub static STATIONARY_CHASSIS: phf::Set<&'static str> = phf_set! {
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "13",
+    "15",
+    "16",
+    "17",
+    "23",
+    "24",
+    "25",
+    "28",
+    "35",
+    "36",
+};
+
+/// A static perfect hash set of placeholder values vendors leave in DMI fields, in lowercase
+pub static DMI_PLACEHOLDERS: phf::Set<&'static str> = phf_set! {
+    "to be filled by o.e.m.",
+    "to be filled by oem",
+    "o.e.m.",
+    "oem",
+    "default string",
+    "system product name",
+    "system version",
+    "system manufacturer",
+    "system name",
+    "base board product name",
+    "base board version",
+    "base board manufacturer",
+    "not applicable",
+    "not specified",
+    "not available",
+    "none",
+    "undefined",
+    "unknown",
+    "type1productconfigid",
+    "invalid",
+    "x.x",
+    "0123456789",
+    "123456789",
+};
+
+/// A static perfect hash map, maping PCI vendor IDs of common GPU vendors to their names
+/// Used when there is no pci.ids database on the system
+pub static PCI_VENDORS: phf::Map<u16, &'static str> = phf_map! {
+    0x1002u16 => "AMD",
+    0x10deu16 => "NVIDIA",
+    0x8086u16 => "Intel",
+    0x1af4u16 => "Red Hat, Inc. (virtio)",
+    0x1234u16 => "QEMU",
+    0x15adu16 => "VMware",
+    0x80eeu16 => "VirtualBox",
+    0x1414u16 => "Microsoft",
+    0x1a03u16 => "ASPEED Technology",
+    0x102bu16 => "Matrox",
+    0x5143u16 => "Qualcomm",
+    0x1ed5u16 => "Moore Threads",
+};
+
+/// A static perfect hash map, maping lowercase XDG_CURRENT_DESKTOP or DESKTOP_SESSION values to desktop environment names
+pub static DESKTOP_ENVIRONMENTS: phf::Map<&'static str, &'static str> = phf_map! {
+    "gnome" => "GNOME",
+    "gnome-xorg" => "GNOME",
+    "gnome-classic" => "GNOME Classic",
+    "kde" => "KDE Plasma",
+    "plasma" => "KDE Plasma",
+    "plasmawayland" => "KDE Plasma",
+    "xfce" => "Xfce",
+    "xfce4" => "Xfce",
+    "mate" => "MATE",
+    "x-cinnamon" => "Cinnamon",
+    "cinnamon" => "Cinnamon",
+    "lxqt" => "LXQt",
+    "lxde" => "LXDE",
+    "budgie" => "Budgie",
+    "budgie-desktop" => "Budgie",
+    "pantheon" => "Pantheon",
+    "deepin" => "Deepin",
+    "unity" => "Unity",
+    "enlightenment" => "Enlightenment",
+    "cosmic" => "COSMIC",
+    "ukui" => "UKUI",
+};
+
+/// A static perfect hash map, maping processes started by desktop environment sessions to desktop environment names
+pub static DE_PROCESSES: phf::Map<&'static str, &'static str> = phf_map! {
+    "gnome-shell" => "GNOME",
+    "gnome-session-b" => "GNOME",
+    "plasmashell" => "KDE Plasma",
+    "ksmserver" => "KDE Plasma",
+    "xfce4-session" => "Xfce",
+    "mate-session" => "MATE",
+    "cinnamon-sessio" => "Cinnamon",
+    "cinnamon" => "Cinnamon",
+    "lxqt-session" => "LXQt",
+    "lxsession" => "LXDE",
+    "budgie-panel" => "Budgie",
+    "gala" => "Pantheon",
+    "dde-session" => "Deepin",
+    "enlightenment" => "Enlightenment",
+    "cosmic-session" => "COSMIC",
+    "ukui-session" => "UKUI",
+};
+
+/// A static perfect hash map, maping process names found in /proc/*/comm (truncated to 15 characters) to window manager and compositor names
+pub static WINDOW_MANAGERS: phf::Map<&'static str, &'static str> = phf_map! {
+    "sway" => "Sway",
+    "Hyprland" => "Hyprland",
+    ".Hyprland-wrapp" => "Hyprland",
+    "kwin_wayland" => "KWin",
+    "kwin_x11" => "KWin",
+    "kwin" => "KWin",
+    "gnome-shell" => "Mutter",
+    "mutter" => "Mutter",
+    "i3" => "i3",
+    "bspwm" => "bspwm",
+    "openbox" => "Openbox",
+    "xfwm4" => "Xfwm4",
+    "marco" => "Marco",
+    "muffin" => "Muffin",
+    "cinnamon" => "Muffin",
+    "awesome" => "awesome",
+    "dwm" => "dwm",
+    "river" => "river",
+    "niri" => "niri",
+    "weston" => "Weston",
+    "labwc" => "labwc",
+    "wayfire" => "Wayfire",
+    "herbstluftwm" => "herbstluftwm",
+    "qtile" => "Qtile",
+    "xmonad" => "xmonad",
+    "fluxbox" => "Fluxbox",
+    "icewm" => "IceWM",
+    "enlightenment" => "Enlightenment",
+    "cosmic-comp" => "cosmic-comp",
+    "gala" => "Gala",
+    "budgie-wm" => "Budgie WM",
+    "kwin_wayland_wr" => "KWin",
+    "miracle-wm" => "miracle-wm",
+    "dwl" => "dwl",
+    "spectrwm" => "spectrwm",
+};
+
+/// A static perfect hash map, maping PNP manufacturer IDs found in monitors' EDID to vendor names
+pub static PNP_VENDORS: phf::Map<&'static str, &'static str> = phf_map! {
+    "ACR" => "Acer",
+    "AOC" => "AOC",
+    "APP" => "Apple",
+    "AUO" => "AU Optronics",
+    "AUS" => "ASUS",
+    "BNQ" => "BenQ",
+    "BOE" => "BOE",
+    "CMN" => "Innolux",
+    "DEL" => "Dell",
+    "EIZ" => "EIZO",
+    "ENC" => "EIZO",
+    "GBT" => "Gigabyte",
+    "GSM" => "LG",
+    "HPN" => "HP",
+    "HWP" => "HP",
+    "IVM" => "iiyama",
+    "LEN" => "Lenovo",
+    "LGD" => "LG Display",
+    "MSI" => "MSI",
+    "NEC" => "NEC",
+    "PHL" => "Philips",
+    "SAM" => "Samsung",
+    "SDC" => "Samsung Display",
+    "SHP" => "Sharp",
+    "SNY" => "Sony",
+    "VSC" => "ViewSonic",
+    "VIZ" => "Vizio",
+    "XMI" => "Xiaomi",
+    "HSD" => "HannStar",
+    "CSO" => "CSOT",
+    "RHT" => "Red Hat (virtual)",
+    "QEM" => "QEMU",
+};
+
+/// A static perfect hash map, maping terminal emulator process names (truncated to 15 characters) to their names
+pub static TERMINALS: phf::Map<&'static str, &'static str> = phf_map! {
+    "gnome-terminal-" => "GNOME Terminal",
+    "kgx" => "GNOME Console",
+    "ptyxis" => "Ptyxis",
+    "ptyxis-agent" => "Ptyxis",
+    "konsole" => "Konsole",
+    "yakuake" => "Yakuake",
+    "kitty" => "kitty",
+    "alacritty" => "Alacritty",
+    "foot" => "foot",
+    "footclient" => "foot",
+    "wezterm-gui" => "WezTerm",
+    "ghostty" => "Ghostty",
+    "xterm" => "xterm",
+    "urxvt" => "urxvt",
+    "urxvtd" => "urxvt",
+    "st" => "st",
+    "terminator" => "Terminator",
+    "tilix" => "Tilix",
+    "xfce4-terminal" => "Xfce Terminal",
+    "mate-terminal" => "MATE Terminal",
+    "lxterminal" => "LXTerminal",
+    "qterminal" => "QTerminal",
+    "terminology" => "Terminology",
+    "sakura" => "Sakura",
+    "tilda" => "Tilda",
+    "deepin-terminal" => "Deepin Terminal",
+    "contour" => "Contour",
+    "rio" => "Rio",
+    "blackbox" => "Black Box",
+    "cool-retro-term" => "cool-retro-term",
+    "code" => "vscode",
+    "kmscon" => "kmscon",
+    "login" => "TTY",
+};
+
+/// A static perfect hash set of shell process names
+pub static SHELLS: phf::Set<&'static str> = phf_set! {
+    "bash",
+    "zsh",
+    "fish",
+    "sh",
+    "dash",
+    "ash",
+    "ksh",
+    "mksh",
+    "oksh",
+    "tcsh",
+    "csh",
+    "yash",
+    "nu",
+    "xonsh",
+    "elvish",
+    "ion",
+    "pwsh",
+    "busybox",
+};
+
+/// A static perfect hash set of processes that can sit between a shell and the terminal, and are skipped when looking for it
+pub static SKIPPED_PROCESSES: phf::Set<&'static str> = phf_set! {
+    "sshd",
+    "sshd-session",
+    "ssh",
+    "mosh-server",
+    "sudo",
+    "sudo-rs",
+    "su",
+    "doas",
+    "run0",
+    "script",
+    "env",
+    "nohup",
+    "time",
+    "strace",
+    "cargo",
+    "rastfetch",
+    "flatpak-spawn",
+};
+
+/// A static perfect hash map, maping shells to commands printing their version
+/// Running the shell is slow, so it's only used when enabled in config
 pub static SHELL_VERSIONS: phf::Map<&'static str, &'static str> = phf_map! {
     "bash" => "echo $BASH_VERSION",
     "zsh" => "echo $ZSH_VERSION",
     "fish" => "echo $FISH_VERSION",
-    "zh" => "echo $ZSH_VERSION",
-    "sh" => "echo $BASH_VERSION"
+    "ksh" => "echo ${.sh.version}",
+    "tcsh" => "echo $tcsh",
+    "nu" => "version | get version",
+    "xonsh" => "echo $XONSH_VERSION",
+    "elvish" => "echo $version",
 };
-*/
\ No newline at end of file
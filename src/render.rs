@@ -0,0 +1,467 @@
+//! This module turns typed module results into output, either colored text with $[1-9] markers or JSON
+//! Labels, units and colors live here, so fetchers in `modules` only have to collect data
+use serde_json::{Map, Value};
+
+use crate::modules::ModuleResult;
+use crate::ASSETS;
+
+/// Renders a result into a string with $[1-9] color markers, ready for `print_colored`
+/// If a format string is given, it replaces the module's default output after the key
+/// Modules with sub-entries are rendered one entry per line
+pub fn render_text(result: &ModuleResult, format: Option<&str>) -> String {
+    if let Some(error) = &result.error {
+        return format!("$3{}: $2{}", result.key, error);
+    }
+    if !result.entries.is_empty() {
+        return result.entries.iter()
+            .map(|entry| render_text(entry, format))
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+    // Modules without any data hide themselves, eg. battery on desktops
+    if result.is_empty() && !matches!(result.module, "separator" | "colors") {
+        return String::new();
+    }
+    if let Some(format) = format {
+        return match apply_format(format, &placeholders(result)) {
+            Ok(value) => format!("$3{}: $2{}", result.key, value),
+            Err(error) => format!("$3{}: $2Config error: {}", result.key, error),
+        };
+    }
+    match result.module {
+        "title" => format!("\n$1{}$2@$1{}$2", result.str_field("user"), result.str_field("hostname")),
+        "separator" => "$2-----------$1".to_string(),
+        "kernel" => format!("$3{}:$2 {} v{}", result.key, result.str_field("name"), result.str_field("version")),
+        "uptime" => format!("$3{}:$2 {}", result.key, format_time(result.u64_field("total_seconds"))),
+        "memory" | "swap" => format!(
+            "$3{}: $2{:.2} $3GiB / $2{:.2} $3GiB $4({}%)$2",
+            result.key,
+            bytes_to_gib(result.u64_field("used_bytes")),
+            bytes_to_gib(result.u64_field("total_bytes")),
+            result.u64_field("percent")
+        ),
+        "cpu" => {
+            let mut cpu = format!("$3{}: $2", result.key);
+            if result.result.contains_key("packages") {
+                cpu.push_str(&format!("{} $3x$2 ", result.u64_field("packages")));
+            }
+            cpu.push_str(result.str_field("name"));
+            if result.result.contains_key("performance_cores") {
+                cpu.push_str(&format!(
+                    " $3($2{}$3P + $2{}$3E, $2{}$3T)$2",
+                    result.u64_field("performance_cores"),
+                    result.u64_field("efficiency_cores"),
+                    result.u64_field("threads")
+                ));
+            } else if result.result.contains_key("cores") {
+                cpu.push_str(&format!(" $3($2{}$3C / $2{}$3T)$2", result.u64_field("cores"), result.u64_field("threads")));
+            }
+            if result.u64_field("frequency_mhz") > 0 {
+                cpu.push_str(&format!(" @ {:.2} $3GHz$2", result.u64_field("frequency_mhz") as f64 / 1000.0));
+            }
+            let caches: Vec<String> = ["l1", "l2", "l3"].iter()
+                .filter(|level| result.result.contains_key(&format!("{}_bytes", level)))
+                .map(|level| format!("{} {}", level.to_uppercase(), format_size(result.u64_field(&format!("{}_bytes", level)))))
+                .collect();
+            if !caches.is_empty() {
+                cpu.push_str(&format!(" - {}", caches.join(", ")));
+            }
+            cpu.push_str(&temperature_suffix(result));
+            cpu
+        }
+        "cpu_usage" => {
+            let mut usage = format!("$3{}: $4{:.2}%$2", result.key, result.f64_field("percent"));
+            if result.result.contains_key("bar") {
+                usage.push_str(&format!(" $3[$4{}$3]$2", result.str_field("bar")));
+            }
+            // Per-core usage follows on separate lines, four CPUs per line
+            let cores: Vec<String> = result.result.get("cores").and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|core| format!("$3{}: $4{:.0}%$2", core["name"].as_str().unwrap_or_default(), core["percent"].as_f64().unwrap_or_default()))
+                .collect();
+            for line in cores.chunks(4) {
+                usage.push_str(&format!("\n{}", line.join("  ")));
+            }
+            usage
+        }
+        "disks" => {
+            let mut disk = format!(
+                "$3{} ({}): $2{:.2} $3GiB / $2{:.2} $3GiB $4({}%)$2",
+                result.key,
+                result.str_field("mount"),
+                bytes_to_gib(result.u64_field("used_bytes")),
+                bytes_to_gib(result.u64_field("total_bytes")),
+                result.u64_field("percent")
+            );
+            // File system is left out when disabled in config
+            if result.result.contains_key("fs") {
+                disk.push_str(&format!(" - {}", result.str_field("fs")));
+            }
+            disk
+        }
+        "colors" => match ASSETS.get_file("ansi/palette.ansi.txt") {
+            Some(file) => file.contents_utf8().unwrap().to_string(),
+            None => "Palette file not found".to_string(),
+        },
+        "bios" => format!("$3{} ({}): $2{} {}$1", result.key, result.str_field("type"), result.str_field("version"), result.str_field("release")),
+        "gpu" => {
+            let mut gpu = format!("$3{}: $2{}", result.key, result.str_field("name"));
+            if result.result.contains_key("driver") {
+                gpu.push_str(&format!(" $3({})$2", result.str_field("driver")));
+            }
+            if result.result.contains_key("vram_bytes") {
+                gpu.push_str(&format!(" {:.2} $3GiB$2", bytes_to_gib(result.u64_field("vram_bytes"))));
+            }
+            gpu.push_str(&temperature_suffix(result));
+            gpu
+        }
+        "packages" => {
+            // Per-scope counts like flatpak-user are already part of their package manager's count
+            let counts: Vec<String> = result.result.iter()
+                .filter(|(manager, _)| *manager != "total" && !manager.contains('-'))
+                .map(|(manager, count)| format!("{} $3({})$2", count, manager))
+                .collect();
+            format!("$3{}: $2{}", result.key, counts.join(", "))
+        }
+        "de" | "wm" => match result.result.get("session_type") {
+            Some(_) => format!("$3{}: $2{} $3({})$2", result.key, result.str_field("name"), result.str_field("session_type")),
+            None => format!("$3{}: $2{}", result.key, result.str_field("name")),
+        },
+        "terminal" => match result.result.get("multiplexer") {
+            Some(_) => format!("$3{}: $2{} $3({})$2", result.key, result.str_field("name"), result.str_field("multiplexer")),
+            None => format!("$3{}: $2{}", result.key, result.str_field("name")),
+        },
+        "shell" => match result.result.get("version") {
+            Some(_) => format!("$3{}:$2 {} {}", result.key, result.str_field("name"), result.str_field("version")),
+            None => format!("$3{}:$2 {}", result.key, result.str_field("name")),
+        },
+        "battery" => {
+            let mut battery = format!("$3{} ({}): $4{}%$2 [{}]", result.key, result.str_field("name"), result.u64_field("percent"), result.str_field("status"));
+            if result.result.contains_key("time_remaining_seconds") {
+                battery.push_str(&format!(" {} $3left$2", format_time(result.u64_field("time_remaining_seconds"))));
+            }
+            if result.result.contains_key("health_percent") {
+                battery.push_str(&format!(" - $3health$2 {}$3%$2", result.u64_field("health_percent")));
+            }
+            if result.result.contains_key("cycle_count") {
+                battery.push_str(&format!(", {} $3cycles$2", result.u64_field("cycle_count")));
+            }
+            if result.result.get("ac_connected").and_then(Value::as_bool) == Some(true) {
+                battery.push_str(" $3(AC connected)$2");
+            }
+            battery
+        }
+        "display" if result.result.contains_key("connector") => {
+            let mut display = format!("$3{} ({}):$2", result.key, result.str_field("connector"));
+            if result.result.contains_key("preferred_width") {
+                display.push_str(&format!(" {}x{}", result.u64_field("preferred_width"), result.u64_field("preferred_height")));
+            }
+            if result.result.contains_key("preferred_refresh_hz") {
+                display.push_str(&format!(" @ {} $3Hz$2", result.f64_field("preferred_refresh_hz").round()));
+            }
+            if result.result.contains_key("preferred_width") {
+                display.push_str(" $3(preferred)$2");
+            }
+            if result.result.contains_key("vendor") {
+                display.push_str(&format!(" - {}", result.str_field("vendor")));
+            }
+            if result.result.contains_key("model") {
+                display.push_str(&format!(" {}", result.str_field("model")));
+            }
+            if result.result.contains_key("diagonal_inches") {
+                display.push_str(&format!(" {}$3\"$2", result.f64_field("diagonal_inches").round()));
+            }
+            display
+        }
+        "network" | "localip" => {
+            let addresses: Vec<&str> = ["ipv4", "ipv6"].iter()
+                .filter_map(|field| result.result.get(*field).and_then(Value::as_array))
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            let addresses = if addresses.is_empty() { "No address".to_string() } else { addresses.join(", ") };
+            let mut network = format!("$3{} ({}): $2{}", result.key, result.str_field("interface"), addresses);
+            let mut details = Vec::new();
+            if result.result.contains_key("operstate") {
+                details.push(result.str_field("operstate").to_string());
+            }
+            if result.result.get("wireless").and_then(Value::as_bool) == Some(true) {
+                details.push("wireless".to_string());
+            }
+            if result.result.contains_key("speed_mbps") {
+                details.push(format!("{} Mb/s", result.u64_field("speed_mbps")));
+            }
+            if result.module == "network" && !details.is_empty() {
+                network.push_str(&format!(" $3[{}]$2", details.join(", ")));
+            }
+            if result.result.contains_key("mac") {
+                network.push_str(&format!(" - {}", result.str_field("mac")));
+            }
+            network
+        }
+        "sensors" => {
+            let mut values: Vec<String> = result.result.get("temperatures").and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|sensor| format!(
+                    "{} {}",
+                    sensor["label"].as_str().unwrap_or_default(),
+                    format_temperature(sensor["celsius"].as_f64().unwrap_or_default(), sensor["level"].as_str())
+                ))
+                .collect();
+            values.extend(
+                result.result.get("fans").and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .map(|fan| format!("{} {} $3RPM$2", fan["label"].as_str().unwrap_or_default(), fan["rpm"]))
+            );
+            format!("$3{} ({}): $2{}", result.key, result.str_field("chip"), values.join(", "))
+        }
+        "host" | "board" => {
+            let parts: Vec<&str> = ["vendor", "name", "version"].into_iter()
+                .filter(|field| result.result.contains_key(*field))
+                .map(|field| result.str_field(field))
+                .collect();
+            format!("$3{}: $2{}", result.key, parts.join(" "))
+        }
+        "virtualization" => {
+            let mut parts: Vec<&str> = ["hypervisor", "container"].into_iter()
+                .filter(|field| result.result.contains_key(*field))
+                .map(|field| result.str_field(field))
+                .collect();
+            if result.result.contains_key("chroot") {
+                parts.push("chroot");
+            }
+            format!("$3{}: $2{}", result.key, parts.join(", "))
+        }
+        "chassis" => format!("$3{}: $2{}", result.key, result.str_field("type")),
+        _ => format!("$3{}:$2 {}", result.key, result.str_field("name")),
+    }
+}
+
+/// Renders all results as a pretty printed JSON array
+/// Modules without any data, like separator, are skipped
+pub fn render_json(results: &[ModuleResult]) -> String {
+    let json_results: Vec<&ModuleResult> = results.iter()
+        .filter(|result| !result.is_empty())
+        .collect();
+    serde_json::to_string_pretty(&json_results).unwrap()
+}
+
+/// Checks a module's format string against every placeholder the module can fill, not only the ones filled on this machine
+/// Unknown modules are not checked, they're reported on their own
+pub fn check_format(module: &str, format: &str) -> Result<(), String> {
+    let Some(names) = placeholder_names(module) else {
+        return Ok(());
+    };
+    let values: Map<String, Value> = names.into_iter().map(|name| (name, Value::Null)).collect();
+    apply_format(format, &values).map(|_| ())
+}
+
+/// Fields each module can fill, those missing on this machine are left out of its result
+fn module_fields(module: &str) -> Option<&'static [&'static str]> {
+    let fields: &[&str] = match module {
+        "title" => &["user", "hostname"],
+        "separator" | "colors" => &[],
+        "os" | "editor" | "platform" => &["name"],
+        "kernel" => &["name", "version"],
+        "uptime" => &["total_seconds"],
+        "memory" | "swap" => &["used_bytes", "total_bytes", "percent"],
+        "shell" => &["name", "version"],
+        "cpu" => &[
+            "name", "brand", "vendor", "packages", "cores", "threads", "performance_cores", "efficiency_cores",
+            "base_frequency_mhz", "max_frequency_mhz", "frequency_mhz", "l1_bytes", "l2_bytes", "l3_bytes",
+            "temperature_celsius", "temperature_level",
+        ],
+        "cpu_usage" => &["percent", "bar", "cores"],
+        "disks" => &["mount", "fs", "used_bytes", "total_bytes", "percent"],
+        "terminal" => &["name", "multiplexer"],
+        "bios" => &["type", "version", "release"],
+        "chassis" => &["code", "type"],
+        "host" | "board" => &["vendor", "name", "version"],
+        "virtualization" => &["hypervisor", "container", "chroot"],
+        "gpu" => &["name", "vendor", "vendor_id", "device_id", "driver", "vram_bytes", "temperature_celsius", "temperature_level"],
+        // Package managers are the ones counted by fetch_packages
+        "packages" => &[
+            "total", "dpkg", "rpm", "pacman", "apk", "xbps", "nix", "nix-system", "nix-default", "nix-user",
+            "flatpak", "flatpak-system", "flatpak-user", "snap",
+        ],
+        "de" | "wm" => &["name", "session_type"],
+        "battery" => &[
+            "name", "model", "percent", "status", "health_percent", "cycle_count", "time_remaining_seconds", "ac_connected",
+        ],
+        "display" => &[
+            "name", "connector", "preferred_width", "preferred_height", "preferred_refresh_hz", "vendor_id", "vendor", "model",
+            "width_mm", "height_mm", "diagonal_inches",
+        ],
+        "network" | "localip" => &["interface", "ipv4", "ipv6", "mac", "operstate", "speed_mbps", "wireless"],
+        "sensors" => &["chip", "temperatures", "fans", "max_celsius", "level"],
+        _ => return None,
+    };
+    Some(fields)
+}
+
+/// Returns the names of all placeholders a module supports, its fields together with their derived variants
+fn placeholder_names(module: &str) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    for field in module_fields(module)? {
+        if let Some(prefix) = field.strip_suffix("_bytes") {
+            names.push(format!("{}_gib", prefix));
+            names.push(format!("{}_mib", prefix));
+        }
+        if let Some(prefix) = field.strip_suffix("_mhz") {
+            names.push(format!("{}_ghz", prefix));
+        }
+        names.push(field.to_string());
+    }
+    if module == "uptime" {
+        names.extend(["days", "hours", "minutes", "seconds"].map(str::to_string));
+    }
+    Some(names)
+}
+
+/// Returns all values a format string can use for the result
+/// Besides the module's own fields, sizes in bytes get GiB and MiB variants, frequencies get GHz and uptime is split into parts
+/// Placeholders the module supports but couldn't fill on this machine show as "Unknown"
+fn placeholders(result: &ModuleResult) -> Map<String, Value> {
+    let mut values: Map<String, Value> = placeholder_names(result.module)
+        .into_iter()
+        .flatten()
+        .map(|name| (name, Value::from("Unknown")))
+        .collect();
+    for (name, value) in &result.result {
+        if let Some(prefix) = name.strip_suffix("_bytes") && let Some(bytes) = value.as_u64() {
+            values.insert(format!("{}_gib", prefix), bytes_to_gib(bytes).into());
+            values.insert(format!("{}_mib", prefix), (bytes as f64 / 1048576.0).into());
+        }
+        if let Some(prefix) = name.strip_suffix("_mhz") && let Some(mhz) = value.as_u64() {
+            values.insert(format!("{}_ghz", prefix), (mhz as f64 / 1000.0).into());
+        }
+        values.insert(name.clone(), value.clone());
+    }
+    if result.module == "uptime" {
+        let total_seconds = result.u64_field("total_seconds");
+        values.insert("days".to_string(), (total_seconds / 86400).into());
+        values.insert("hours".to_string(), (total_seconds % 86400 / 3600).into());
+        values.insert("minutes".to_string(), (total_seconds % 3600 / 60).into());
+        values.insert("seconds".to_string(), (total_seconds % 60).into());
+    }
+    values
+}
+
+/// Replaces `{name}` and `{name:.N}` placeholders in the format string with values, `{{` and `}}` print literal braces
+/// Returns an error naming the placeholder if it's unknown or malformed
+fn apply_format(format: &str, values: &Map<String, Value>) -> Result<String, String> {
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(format!("unclosed placeholder {{{} in format", placeholder)),
+                    }
+                }
+                output.push_str(&format_placeholder(&placeholder, values)?);
+            }
+            _ => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+/// Formats a single placeholder, with optional precision for numbers
+fn format_placeholder(placeholder: &str, values: &Map<String, Value>) -> Result<String, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+    let Some(value) = values.get(name) else {
+        let mut available: Vec<&str> = values.keys().map(String::as_str).collect();
+        available.sort();
+        return Err(format!("unknown placeholder {{{}}} in format, available: {}", name, available.join(", ")));
+    };
+    let precision = match spec {
+        Some(spec) => match spec.strip_prefix('.').and_then(|digits| digits.parse::<usize>().ok()) {
+            Some(precision) => Some(precision),
+            None => return Err(format!("invalid format spec \"{}\" in {{{}}}, expected .N", spec, placeholder)),
+        },
+        None => None,
+    };
+    let text = match (value, precision) {
+        (Value::Number(number), Some(precision)) => format!("{:.*}", precision, number.as_f64().unwrap_or(0.0)),
+        (Value::Number(number), None) if number.is_f64() => format!("{:.2}", number.as_f64().unwrap_or(0.0)),
+        (Value::String(text), _) => text.clone(),
+        (Value::Array(items), _) => items.iter()
+            .map(|item| item.as_str().map(str::to_string).unwrap_or(item.to_string()))
+            .collect::<Vec<String>>()
+            .join(", "),
+        (value, _) => value.to_string(),
+    };
+    Ok(text)
+}
+
+/// Returns " - 45.0°C" for results with a temperature, or nothing
+fn temperature_suffix(result: &ModuleResult) -> String {
+    match result.result.get("temperature_celsius").and_then(Value::as_f64) {
+        Some(celsius) => format!(" - {}", format_temperature(celsius, result.result.get("temperature_level").and_then(Value::as_str))),
+        None => String::new(),
+    }
+}
+
+/// Formats a temperature, colored yellow from the high threshold and red from the critical one with fixed $0 markers
+fn format_temperature(celsius: f64, level: Option<&str>) -> String {
+    let color = match level {
+        Some("critical") => "$0r",
+        Some("high") => "$0y",
+        _ => "$2",
+    };
+    format!("{}{:.1}$3°C$2", color, celsius)
+}
+
+/// Formats a size with the largest binary unit that keeps it at least 1, eg. "640 $3KiB$2" or "1.25 $3MiB$2"
+fn format_size(bytes: u64) -> String {
+    let (value, unit) = match bytes {
+        0..1024 => (bytes as f64, "B"),
+        1024..1048576 => (bytes as f64 / 1024.0, "KiB"),
+        1048576..1073741824 => (bytes as f64 / 1048576.0, "MiB"),
+        _ => (bytes_to_gib(bytes), "GiB"),
+    };
+    format!("{} $3{}$2", (value * 100.0).round() / 100.0, unit)
+}
+
+fn bytes_to_gib(bytes: u64) -> f64 {
+    bytes as f64 / 1073741824.0
+}
+
+/// Formats uptime in a pretty way
+fn format_time(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let seconds_left_after_hours = seconds % 3600;
+    let minutes = seconds_left_after_hours / 60;
+    let seconds = seconds_left_after_hours % 60;
+
+    let mut parts = Vec::new();
+
+    if hours > 0 {
+        parts.push(format!("$2{}$3h$2", hours));
+    }
+    if minutes > 0 || hours > 0 {
+        parts.push(format!("$2{}$3m$2", minutes));
+    }
+    parts.push(format!("$2{}$3s$2", seconds));
+
+    parts.join(" ")
+}
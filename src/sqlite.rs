@@ -0,0 +1,188 @@
+//! This module contains a minimal read-only SQLite file reader, just enough to count rows of a table
+//! Used for package databases like rpm's rpmdb.sqlite, without linking the whole SQLite library
+//! Changes that are still in the write-ahead log (-wal file) are not seen
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+/// Page types of table b-trees, see https://www.sqlite.org/fileformat.html#b_tree_pages
+const INTERIOR_TABLE_PAGE: u8 = 0x05;
+const LEAF_TABLE_PAGE: u8 = 0x0d;
+
+/// Returns the number of rows in a table of an SQLite database file, or None if the file or table can't be read
+/// Only the pages of the schema and of the table are read, as package databases can be hundreds of megabytes
+pub fn count_rows(path: &Path, table: &str) -> Option<usize> {
+    let file = File::open(path).ok()?;
+    let mut header = [0; 100];
+    file.read_exact_at(&mut header, 0).ok()?;
+    if !header.starts_with(b"SQLite format 3\0") {
+        return None;
+    }
+    let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+        1 => 65536,
+        size => size as usize,
+    };
+    let reader = Reader { file, page_size };
+
+    // Find the root page of the table in the schema table, which is rooted at page 1
+    let mut root_page = None;
+    reader.visit_rows(1, 0, &mut |payload| {
+        let columns = record_columns(payload, 4);
+        if let [Some(Column::Text(kind)), Some(Column::Text(name)), _, Some(Column::Int(root))] = columns.as_slice()
+            && *kind == b"table" && name.eq_ignore_ascii_case(table.as_bytes()) {
+            root_page = Some(*root as usize);
+        }
+    })?;
+
+    let mut count = 0;
+    reader.visit_rows(root_page?, 0, &mut |_| count += 1)?;
+    Some(count)
+}
+
+struct Reader {
+    file: File,
+    page_size: usize,
+}
+
+impl Reader {
+    /// Reads a whole page, pages are numbered from 1
+    fn read_page(&self, page_number: usize) -> Option<Vec<u8>> {
+        let mut page = vec![0; self.page_size];
+        self.file.read_exact_at(&mut page, ((page_number - 1) * self.page_size) as u64).ok()?;
+        Some(page)
+    }
+
+    /// Calls `visit` with the local payload of every row in the table b-tree rooted at `page_number`
+    fn visit_rows(&self, page_number: usize, depth: usize, visit: &mut dyn FnMut(&[u8])) -> Option<()> {
+        // Guard against loops in corrupted files
+        if depth > 64 || page_number == 0 {
+            return None;
+        }
+        let page = self.read_page(page_number)?;
+        // Page 1 starts with the 100 byte file header
+        let header = if page_number == 1 { 100 } else { 0 };
+        let page_type = *page.get(header)?;
+        let cell_count = u16::from_be_bytes([*page.get(header + 3)?, *page.get(header + 4)?]) as usize;
+        let pointers = header + if page_type == INTERIOR_TABLE_PAGE { 12 } else { 8 };
+
+        for index in 0..cell_count {
+            let pointer = pointers + index * 2;
+            let cell = u16::from_be_bytes([*page.get(pointer)?, *page.get(pointer + 1)?]) as usize;
+            let cell = page.get(cell..)?;
+            match page_type {
+                LEAF_TABLE_PAGE => {
+                    let (payload_size, size_len) = read_varint(cell)?;
+                    let (_, rowid_len) = read_varint(&cell[size_len..])?;
+                    let payload = &cell[size_len + rowid_len..];
+                    visit(&payload[..payload.len().min(payload_size as usize)]);
+                }
+                INTERIOR_TABLE_PAGE => {
+                    let child = u32::from_be_bytes(cell.get(..4)?.try_into().ok()?) as usize;
+                    self.visit_rows(child, depth + 1, visit)?;
+                }
+                _ => return None,
+            }
+        }
+        if page_type == INTERIOR_TABLE_PAGE {
+            let right_most = u32::from_be_bytes(page.get(header + 8..header + 12)?.try_into().ok()?) as usize;
+            self.visit_rows(right_most, depth + 1, visit)?;
+        }
+        Some(())
+    }
+}
+
+/// Value of a record column, only the types needed for reading the schema
+enum Column<'a> {
+    Int(i64),
+    Text(&'a [u8]),
+    Other,
+}
+
+/// Decodes up to `limit` leading columns of a record, columns that don't fit in the local payload are None
+fn record_columns(payload: &[u8], limit: usize) -> Vec<Option<Column<'_>>> {
+    let mut columns = Vec::new();
+    let Some((header_size, mut header_pos)) = read_varint(payload) else {
+        return columns;
+    };
+    let mut body_pos = header_size as usize;
+    while header_pos < header_size as usize && columns.len() < limit {
+        let Some((serial_type, len)) = payload.get(header_pos..).and_then(read_varint) else {
+            break;
+        };
+        header_pos += len;
+        let size = match serial_type {
+            0 | 8 | 9 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            n if n >= 12 => (n as usize - 12) / 2,
+            _ => 0,
+        };
+        let value = payload.get(body_pos..body_pos + size).map(|bytes| match serial_type {
+            1..=6 => {
+                // Big-endian two's complement integer of `size` bytes
+                let sign = if bytes[0] & 0x80 != 0 { -1i64 } else { 0 };
+                Column::Int(bytes.iter().fold(sign, |acc, byte| (acc << 8) | *byte as i64))
+            }
+            8 => Column::Int(0),
+            9 => Column::Int(1),
+            n if n >= 13 && n % 2 == 1 => Column::Text(bytes),
+            _ => Column::Other,
+        });
+        body_pos += size;
+        columns.push(value);
+    }
+    columns
+}
+
+/// Reads an SQLite varint, returns its value and length in bytes
+fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (index, byte) in bytes.iter().take(9).enumerate() {
+        if index == 8 {
+            return Some(((value << 8) | *byte as u64, 9));
+        }
+        value = (value << 7) | (*byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints() {
+        assert_eq!(read_varint(&[0x00]), Some((0, 1)));
+        assert_eq!(read_varint(&[0x7f, 0xff]), Some((127, 1)));
+        assert_eq!(read_varint(&[0x81, 0x00]), Some((128, 2)));
+        assert_eq!(read_varint(&[0x82, 0x80, 0x01]), Some((32769, 3)));
+        // The ninth byte contributes all of its 8 bits
+        assert_eq!(read_varint(&[0xff; 9]), Some((u64::MAX, 9)));
+        // Truncated in the middle of a varint
+        assert_eq!(read_varint(&[0x81, 0x80]), None);
+        assert_eq!(read_varint(&[]), None);
+    }
+
+    #[test]
+    fn schema_record() {
+        // Header of 5 bytes: text of 5 bytes, 1 byte integer, constant 1 and 2 byte integer, followed by the values
+        let payload = [5, 23, 1, 9, 2, b't', b'a', b'b', b'l', b'e', 0xff, 0x01, 0x00];
+        let columns = record_columns(&payload, 4);
+        assert_eq!(columns.len(), 4);
+        assert!(matches!(columns[0], Some(Column::Text(b"table"))));
+        assert!(matches!(columns[1], Some(Column::Int(-1))));
+        assert!(matches!(columns[2], Some(Column::Int(1))));
+        assert!(matches!(columns[3], Some(Column::Int(256))));
+
+        assert_eq!(record_columns(&payload, 2).len(), 2);
+        // A value cut off by the end of the local payload is None, the columns before it are still decoded
+        let columns = record_columns(&payload[..12], 4);
+        assert!(matches!(columns[1], Some(Column::Int(-1))));
+        assert!(columns[3].is_none());
+        assert!(record_columns(&[], 4).is_empty());
+    }
+}